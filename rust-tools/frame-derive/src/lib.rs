@@ -0,0 +1,636 @@
+/* Derive macro for bit-field frame layouts, used by the time code decoders
+ * of rust-tools. See `bit_frame` there for the attributes and what gets
+ * generated; the generated code refers to `crate::bit_frame`,
+ * `crate::bitrep` and `crate::frame_view`.
+ */
+use std::ops::Range;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::meta::ParseNestedMeta;
+use syn::parse::ParseStream;
+use syn::punctuated::Punctuated;
+use syn::{Data, DeriveInput, Error, Fields, Ident, LitInt, LitStr, Result, Token, Type, Visibility, bracketed, parse_macro_input};
+
+enum Encoding {
+    Flag,
+    Bcd(Vec<usize>),
+    Binary,
+    Enumeration,
+    Raw
+}
+
+struct FieldDef {
+    ident: Ident,
+    ty: Type,
+    bits: Range<usize>,
+    // Markers or unused bits within the field
+    skip: Vec<usize>,
+    encoding: Encoding,
+    msb: bool,
+    labels: Vec<String>,
+    header: Option<String>
+}
+
+struct MarkerDef {
+    name: String,
+    bits: Vec<usize>,
+    // Given as a list rather than a range
+    list: bool,
+    label: String,
+    skipped: bool
+}
+
+struct ParityDef {
+    name: String,
+    bits: Range<usize>,
+    label: String,
+    odd: bool
+}
+
+struct FrameDef {
+    len: usize,
+    // The enum generated for the parity groups, see `expand_parity_enum`
+    parity_enum: Option<Ident>,
+    fields: Vec<FieldDef>,
+    markers: Vec<MarkerDef>,
+    parities: Vec<ParityDef>
+}
+
+#[proc_macro_derive(BitFrame, attributes(frame, fixed, parity, field))]
+pub fn derive_bit_frame(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match parse_frame(&input).and_then(|frame| expand(&input.ident, &input.vis, &frame)) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into()
+    }
+}
+
+/***************************************************************************************************
+ * Attribute parsing
+ */
+
+// `n` or `start..end`
+fn parse_bits(input: ParseStream) -> Result<Range<usize>> {
+    let start: usize = input.parse::<LitInt>()?.base10_parse()?;
+    if input.peek(Token![..]) {
+        input.parse::<Token![..]>()?;
+        let end: usize = input.parse::<LitInt>()?.base10_parse()?;
+        if end <= start {
+            return Err(input.error("empty bit range"))
+        }
+        Ok(start..end)
+    } else {
+        Ok(start..start + 1)
+    }
+}
+
+// `[n, start..end, ...]`
+fn parse_bit_list(input: ParseStream) -> Result<Vec<usize>> {
+    let content;
+    bracketed!(content in input);
+    Ok(Punctuated::<Range<usize>, Token![,]>::parse_terminated_with(&content, parse_bits)?
+        .into_iter()
+        .flatten()
+        .collect())
+}
+
+fn parse_widths(input: ParseStream) -> Result<Vec<usize>> {
+    let content;
+    bracketed!(content in input);
+    Punctuated::<LitInt, Token![,]>::parse_terminated(&content)?
+        .iter()
+        .map(|width| width.base10_parse())
+        .collect()
+}
+
+fn parse_labels(input: ParseStream) -> Result<Vec<String>> {
+    let content;
+    bracketed!(content in input);
+    Ok(Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?
+        .iter()
+        .map(|label| label.value())
+        .collect())
+}
+
+fn string_value(meta: &ParseNestedMeta) -> Result<String> {
+    Ok(meta.value()?.parse::<LitStr>()?.value())
+}
+
+fn bits_value(meta: &ParseNestedMeta) -> Result<Range<usize>> {
+    parse_bits(meta.value()?)
+}
+
+fn parse_field(field: &syn::Field) -> Result<Option<FieldDef>> {
+    let Some(attr) = field.attrs.iter().find(|attr| attr.path().is_ident("field")) else {
+        return Ok(None)
+    };
+    let ident = field.ident.clone()
+        .ok_or_else(|| Error::new_spanned(field, "BitFrame fields need a name"))?;
+    let mut bits = None;
+    let mut skip = Vec::new();
+    let mut encoding = Encoding::Flag;
+    let mut msb = false;
+    let mut labels = Vec::new();
+    let mut header = None;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("bits") {
+            bits = Some(bits_value(&meta)?);
+        } else if meta.path.is_ident("skip") {
+            skip = parse_bit_list(meta.value()?)?;
+        } else if meta.path.is_ident("bcd") {
+            encoding = Encoding::Bcd(parse_widths(meta.value()?)?);
+        } else if meta.path.is_ident("binary") {
+            encoding = Encoding::Binary;
+        } else if meta.path.is_ident("enumeration") {
+            encoding = Encoding::Enumeration;
+        } else if meta.path.is_ident("raw") {
+            encoding = Encoding::Raw;
+        } else if meta.path.is_ident("msb") {
+            msb = true;
+        } else if meta.path.is_ident("label") {
+            labels = vec![string_value(&meta)?];
+        } else if meta.path.is_ident("labels") {
+            labels = parse_labels(meta.value()?)?;
+        } else if meta.path.is_ident("header") {
+            header = Some(string_value(&meta)?);
+        } else {
+            return Err(meta.error("expected bits, skip, bcd, binary, enumeration, raw, msb, label, labels or header"))
+        }
+        Ok(())
+    })?;
+    let bits = bits.ok_or_else(|| Error::new_spanned(attr, "missing bits = start..end"))?;
+    if skip.iter().any(|n| !bits.contains(n) || *n == bits.start) {
+        return Err(Error::new_spanned(attr, "skipped bits have to be inside the field"))
+    }
+    let width = bits.len() - skip.len();
+
+    match encoding {
+        Encoding::Flag if bits.len() != 1 =>
+            return Err(Error::new_spanned(attr, "flags are a single bit")),
+        // Decoded through a buffer of `bit_frame::MAX_FIELD_BITS`
+        Encoding::Bcd(_) | Encoding::Binary | Encoding::Enumeration if width > 32 =>
+            return Err(Error::new_spanned(attr, "fields other than raw ones have at most 32 bits")),
+        Encoding::Bcd(ref widths) if widths.iter().sum::<usize>() != width =>
+            return Err(Error::new_spanned(attr, "BCD digit widths do not add up to the bits")),
+        Encoding::Bcd(ref widths) if labels.len() != widths.len() =>
+            return Err(Error::new_spanned(attr, "BCD fields need one label per digit")),
+        _ => ()
+    }
+    if labels.is_empty() {
+        return Err(Error::new_spanned(attr, "missing label"))
+    }
+    if let Some(ref header) = header
+        && header.chars().count() != bits.len() {
+        return Err(Error::new_spanned(attr, "header needs one character per bit"))
+    }
+    let def = FieldDef { ident, ty: field.ty.clone(), bits, skip, encoding, msb, labels, header };
+    // The view shows each digit in one piece
+    if let Encoding::Bcd(ref widths) = def.encoding
+        && digits(&def.positions(), widths).iter().any(|digit| runs(digit).len() > 1) {
+        return Err(Error::new_spanned(attr, "BCD digits cannot be split by skipped bits"))
+    }
+    Ok(Some(def))
+}
+
+impl FieldDef {
+    fn positions(&self) -> Vec<usize> {
+        self.bits.clone().filter(|n| !self.skip.contains(n)).collect()
+    }
+}
+
+// The positions of each digit, in the order sent
+fn digits(positions: &[usize], widths: &[usize]) -> Vec<Vec<usize>> {
+    let mut rest = positions;
+    widths.iter()
+        .map(|width| {
+            let (digit, tail) = rest.split_at(*width);
+            rest = tail;
+            digit.to_vec()
+        })
+        .collect()
+}
+
+// Contiguous runs of positions
+fn runs(positions: &[usize]) -> Vec<Range<usize>> {
+    let mut runs: Vec<Range<usize>> = Vec::new();
+    for n in positions {
+        match runs.last_mut() {
+            Some(run) if run.end == *n => run.end += 1,
+            _ => runs.push(*n..*n + 1),
+        }
+    }
+    runs
+}
+
+fn parse_marker(attr: &syn::Attribute) -> Result<MarkerDef> {
+    let mut name = None;
+    let mut bits = None;
+    let mut label = None;
+    let mut skipped = false;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(string_value(&meta)?);
+        } else if meta.path.is_ident("bits") {
+            let value = meta.value()?;
+            bits = Some(if value.peek(syn::token::Bracket) {
+                (parse_bit_list(value)?, true)
+            } else {
+                (parse_bits(value)?.collect(), false)
+            });
+        } else if meta.path.is_ident("label") {
+            label = Some(string_value(&meta)?);
+        } else if meta.path.is_ident("skipped") {
+            skipped = true;
+        } else {
+            return Err(meta.error("expected name, bits, label or skipped"))
+        }
+        Ok(())
+    })?;
+    match (name, bits, label) {
+        (Some(name), Some((bits, list)), Some(label)) if !bits.is_empty() => Ok(MarkerDef { name, bits, list, label, skipped }),
+        _ => Err(Error::new_spanned(attr, "markers need a name, bits and a label"))
+    }
+}
+
+fn parse_parity(attr: &syn::Attribute) -> Result<ParityDef> {
+    let mut name = None;
+    let mut bits = None;
+    let mut label = None;
+    let mut odd = false;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(string_value(&meta)?);
+        } else if meta.path.is_ident("bits") {
+            bits = Some(bits_value(&meta)?);
+        } else if meta.path.is_ident("label") {
+            label = Some(string_value(&meta)?);
+        } else if meta.path.is_ident("odd") {
+            odd = true;
+        } else {
+            return Err(meta.error("expected name, bits, label or odd"))
+        }
+        Ok(())
+    })?;
+    match (name, bits, label) {
+        (Some(name), Some(bits), Some(label)) if bits.len() >= 2 => Ok(ParityDef { name, bits, label, odd }),
+        _ => Err(Error::new_spanned(attr, "parity groups need a name, at least two bits and a label"))
+    }
+}
+
+fn parse_frame(input: &DeriveInput) -> Result<FrameDef> {
+    let Data::Struct(ref data) = input.data else {
+        return Err(Error::new_spanned(input, "BitFrame can only be derived for structs"))
+    };
+    let Fields::Named(ref named) = data.fields else {
+        return Err(Error::new_spanned(input, "BitFrame needs named fields"))
+    };
+
+    let mut len = None;
+    let mut parity_enum = None;
+    let mut markers = Vec::new();
+    let mut parities = Vec::new();
+    for attr in &input.attrs {
+        if attr.path().is_ident("frame") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("len") {
+                    len = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("parity") {
+                    parity_enum = Some(meta.value()?.parse::<Ident>()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected len or parity"))
+                }
+            })?;
+        } else if attr.path().is_ident("fixed") {
+            markers.push(parse_marker(attr)?);
+        } else if attr.path().is_ident("parity") {
+            parities.push(parse_parity(attr)?);
+        }
+    }
+    let len = len.ok_or_else(|| Error::new_spanned(input, "missing #[frame(len = ...)]"))?;
+    match parity_enum {
+        None if !parities.is_empty() =>
+            return Err(Error::new_spanned(input, "parity groups need an enum to name them, #[frame(parity = ...)]")),
+        Some(ref ident) if parities.is_empty() =>
+            return Err(Error::new_spanned(ident, "no parity groups to name")),
+        _ => ()
+    }
+
+    let mut fields = Vec::new();
+    for field in &named.named {
+        match parse_field(field)? {
+            Some(def) => fields.push(def),
+            None => return Err(Error::new_spanned(field, "missing #[field(...)]"))
+        }
+    }
+
+    // Every bit belongs to at most one field, marker or parity bit
+    let mut owner: Vec<Option<String>> = vec![None; len];
+    let items = fields.iter().map(|f| (f.ident.to_string(), f.positions()))
+        .chain(markers.iter().map(|m| (m.name.clone(), m.bits.clone())))
+        .chain(parities.iter().map(|p| (format!("{} parity", p.name), vec![p.bits.end - 1])));
+    for (name, bits) in items {
+        if bits.iter().any(|n| *n >= len) {
+            return Err(Error::new(Span::call_site(), format!("{} is outside the frame", name)))
+        }
+        for n in bits {
+            if let Some(ref other) = owner[n] {
+                return Err(Error::new(Span::call_site(), format!("bit {} is used by {} and {}", n, other, name)))
+            }
+            owner[n] = Some(name.clone());
+        }
+    }
+    Ok(FrameDef { len, parity_enum, fields, markers, parities })
+}
+
+/***************************************************************************************************
+ * Code generation
+ */
+
+fn upper_ident(name: &str, suffix: &str) -> Ident {
+    format_ident!("{}{}", name.to_uppercase().replace(' ', "_"), suffix)
+}
+
+// "day of week" -> DayOfWeek
+fn variant_ident(name: &str) -> Ident {
+    let camel: String = name.split([' ', '_'])
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars.next().map(|first| first.to_ascii_uppercase()).into_iter().chain(chars)
+        })
+        .collect();
+    format_ident!("{}", camel)
+}
+
+fn range_tokens(bits: &Range<usize>) -> proc_macro2::TokenStream {
+    let (start, end) = (bits.start, bits.end);
+    quote!(#start..#end)
+}
+
+// One character per bit, like the encoding tables in the decoder modules
+fn header(frame: &FrameDef) -> String {
+    let mut header = vec!['-'; frame.len];
+    for field in &frame.fields {
+        let chars: Vec<char> = match (&field.encoding, &field.header) {
+            (_, Some(header)) => header.chars().collect(),
+            (Encoding::Flag, None) => field.labels[0].chars().take(1).collect(),
+            (Encoding::Bcd(widths), None) => widths.iter()
+                .flat_map(|width| {
+                    let weights: Vec<char> = "1248".chars().take(*width).collect();
+                    if field.msb { weights.into_iter().rev().collect() } else { weights }
+                })
+                .collect(),
+            (Encoding::Binary | Encoding::Enumeration | Encoding::Raw, None) => vec!['-'; field.bits.len()],
+        };
+        for (n, c) in field.positions().into_iter().zip(chars) {
+            header[n] = c;
+        }
+    }
+    for marker in frame.markers.iter().filter(|marker| marker.skipped) {
+        for n in &marker.bits {
+            header[*n] = '_';
+        }
+    }
+    for parity in &frame.parities {
+        header[parity.bits.end - 1] = 'P';
+    }
+    header.into_iter().collect()
+}
+
+// (start, label, bits, kind) of each span of the rendered frame
+fn spans(frame: &FrameDef) -> Vec<(usize, String, Range<usize>, &'static str)> {
+    let mut spans = Vec::new();
+    for field in &frame.fields {
+        let positions = field.positions();
+        let number = if field.msb { "BcdMsb" } else { "Bcd" };
+        match field.encoding {
+            Encoding::Bcd(ref widths) => {
+                for (digit, label) in digits(&positions, widths).iter().zip(&field.labels) {
+                    let bits = digit[0]..digit[digit.len() - 1] + 1;
+                    spans.push((bits.start, label.clone(), bits, number));
+                }
+            }
+            Encoding::Enumeration | Encoding::Flag if field.labels.len() == positions.len() => {
+                for (n, label) in positions.into_iter().zip(&field.labels) {
+                    spans.push((n, label.clone(), n..n + 1, "Flag"));
+                }
+            }
+            // Split by skipped bits, each part shows its own value
+            Encoding::Enumeration | Encoding::Flag | Encoding::Binary | Encoding::Raw => {
+                let kind = match field.encoding {
+                    Encoding::Enumeration | Encoding::Flag => number,
+                    _ => "Raw"
+                };
+                for run in runs(&positions) {
+                    spans.push((run.start, field.labels[0].clone(), run, kind));
+                }
+            }
+        }
+    }
+    for marker in &frame.markers {
+        for run in runs(&marker.bits) {
+            spans.push((run.start, marker.label.clone(), run, "Marker"));
+        }
+    }
+    for parity in &frame.parities {
+        let n = parity.bits.end - 1;
+        spans.push((n, parity.label.clone(), n..n + 1, "Parity"));
+    }
+    spans.sort_by_key(|span| span.0);
+    spans
+}
+
+fn bcd_tokens(widths: &[usize], msb: bool) -> proc_macro2::TokenStream {
+    let order = if msb { quote!(MsbFirst) } else { quote!(LsbFirst) };
+    quote!(crate::bitrep::Bcd { widths: &[#(#widths),*], order: crate::bitrep::BcdOrder::#order })
+}
+
+/* One variant per parity group, for `FrameError::ParityError`. Serde
+    support follows the crate's "serde" feature, like the frames using it.
+ */
+fn expand_parity_enum(ident: &Ident, vis: &Visibility, frame: &FrameDef) -> proc_macro2::TokenStream {
+    let count = frame.parities.len();
+    let variants: Vec<Ident> = frame.parities.iter().map(|parity| variant_ident(&parity.name)).collect();
+    let names = frame.parities.iter().map(|parity| &parity.name);
+    let ranges = frame.parities.iter().map(|parity| range_tokens(&parity.bits));
+    quote! {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #vis enum #ident {
+            #(#variants),*
+        }
+
+        impl #ident {
+            pub const ALL: [Self; #count] = [#(Self::#variants),*];
+
+            // The bits covered by the parity, including the parity bit itself
+            pub fn bit_range(&self) -> ::core::ops::Range<usize> {
+                match *self {
+                    #(Self::#variants => #ranges),*
+                }
+            }
+
+            // As in the layout
+            pub fn name(&self) -> &'static str {
+                match *self {
+                    #(Self::#variants => #names),*
+                }
+            }
+        }
+
+        impl ::core::fmt::Display for #ident {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.write_str(self.name())
+            }
+        }
+    }
+}
+
+fn expand(ident: &Ident, vis: &Visibility, frame: &FrameDef) -> Result<proc_macro2::TokenStream> {
+    let len = frame.len;
+    let parity_ty = match frame.parity_enum {
+        Some(ref parity) => quote!(#parity),
+        None => quote!(::core::convert::Infallible)
+    };
+    let error_ty = quote!(crate::bit_frame::FrameError<#parity_ty>);
+    let parity_enum = frame.parity_enum.as_ref().map(|parity| expand_parity_enum(parity, vis, frame));
+    let header = header(frame);
+    let layout = spans(frame).into_iter().map(|(_, label, bits, kind)| {
+        let bits = range_tokens(&bits);
+        let kind = format_ident!("{}", kind);
+        quote!(crate::frame_view::span(#label, #bits, crate::frame_view::SpanKind::#kind))
+    });
+
+    let mut consts = Vec::new();
+    let mut methods = Vec::new();
+    let mut decoded = Vec::new();
+    let mut encoded = Vec::new();
+    let mut encoded_parities = Vec::new();
+
+    for parity in &frame.parities {
+        let name = &parity.name;
+        let odd = parity.odd;
+        let bits = range_tokens(&parity.bits);
+        let const_ident = upper_ident(name, "_PARITY_BITS");
+        let check = format_ident!("check_{}_parity", name.replace(' ', "_"));
+        let variant = variant_ident(name);
+        consts.push(quote!(pub const #const_ident: ::core::ops::Range<usize> = #bits;));
+        methods.push(quote! {
+            pub fn #check(bits: &[crate::bitrep::Bit]) -> ::core::result::Result<(), #error_ty> {
+                crate::bit_frame::check_parity(#parity_ty::#variant, bits, #bits, #odd)
+            }
+        });
+        encoded_parities.push(quote!(crate::bit_frame::encode_parity(bits, #bits, #odd);));
+    }
+
+    for marker in &frame.markers {
+        let const_ident = upper_ident(&marker.name, "_BITS");
+        let positions = &marker.bits;
+        if marker.list {
+            consts.push(quote!(pub const #const_ident: &'static [usize] = &[#(#positions),*];));
+        } else {
+            let bits = range_tokens(&(positions[0]..positions[positions.len() - 1] + 1));
+            consts.push(quote!(pub const #const_ident: ::core::ops::Range<usize> = #bits;));
+        }
+        if marker.skipped {
+            encoded.push(quote!(#(bits[#positions] = crate::bitrep::Bit::Skipped;)*));
+        }
+    }
+    let sync_checks = frame.markers.iter().filter(|marker| marker.skipped).map(|marker| {
+        let positions = &marker.bits;
+        quote! {
+            if [#(#positions),*].iter().any(|n| bits[*n] != crate::bitrep::Bit::Skipped) {
+                return Err(crate::bit_frame::FrameError::NotSync)
+            }
+        }
+    });
+
+    for field in &frame.fields {
+        let field_ident = &field.ident;
+        let ty = &field.ty;
+        let name = field_ident.to_string();
+        let bits = range_tokens(&field.bits);
+        let positions = field.positions();
+        let positions = quote!(&[#(#positions),*]);
+        let const_ident = upper_ident(&name, "_BITS");
+        let decode = format_ident!("decode_{}", name);
+        let msb = field.msb;
+        consts.push(quote!(pub const #const_ident: ::core::ops::Range<usize> = #bits;));
+
+        // Check the parity covering the field first
+        let parity_checks = frame.parities.iter()
+            .filter(|parity| parity.bits.start <= field.bits.start && field.bits.end < parity.bits.end)
+            .map(|parity| {
+                let check = format_ident!("check_{}_parity", parity.name.replace(' ', "_"));
+                quote!(Self::#check(bits)?;)
+            });
+        let (value, encode) = match field.encoding {
+            Encoding::Flag => {
+                let n = field.bits.start;
+                (quote!(crate::bit_frame::decode_flag(bits, #n)),
+                 quote!(bits[#n] = crate::bitrep::Bit::from(self.#field_ident);))
+            }
+            Encoding::Bcd(ref widths) => {
+                let bcd = bcd_tokens(widths, msb);
+                (quote!(crate::bit_frame::decode_bcd(#name, bits, #positions, #bcd).map(|value| value as #ty)),
+                 quote!(crate::bit_frame::encode_bcd(#name, bits, #positions, #bcd, self.#field_ident as u32)?;))
+            }
+            Encoding::Binary =>
+                (quote!(crate::bit_frame::decode_binary(bits, #positions, #msb).map(|value| value as #ty)),
+                 quote!(crate::bit_frame::encode_binary(#name, bits, #positions, #msb, self.#field_ident as u32)?;)),
+            Encoding::Enumeration =>
+                (quote!(crate::bit_frame::decode_enum::<#ty, _>(#name, bits, #positions, #msb)),
+                 quote!(crate::bit_frame::encode_enum(#name, bits, #positions, #msb, &self.#field_ident)?;)),
+            Encoding::Raw =>
+                (quote!(Ok(crate::bit_frame::decode_raw(bits, #positions))),
+                 quote!(crate::bit_frame::encode_raw(#name, bits, #positions, &self.#field_ident)?;)),
+        };
+        methods.push(quote! {
+            pub fn #decode(bits: &[crate::bitrep::Bit]) -> ::core::result::Result<#ty, #error_ty> {
+                #(#parity_checks)*
+                #value
+            }
+        });
+        decoded.push(quote!(#field_ident: Self::#decode(bits)?));
+        encoded.push(encode);
+    }
+
+    Ok(quote! {
+        #parity_enum
+
+        // A layout doesn't have to use every accessor it gets
+        #[allow(dead_code)]
+        impl #ident {
+            #(#consts)*
+            #(#methods)*
+        }
+
+        impl crate::bit_frame::BitFrame for #ident {
+            type Parity = #parity_ty;
+            const LEN: usize = #len;
+            const HEADER: &'static str = #header;
+            const LAYOUT: &'static [crate::frame_view::FieldSpan] = &[#(#layout),*];
+
+            fn decode_bits(bits: &[crate::bitrep::Bit]) -> ::core::result::Result<Self, #error_ty> {
+                if bits.len() != Self::LEN {
+                    return Err(crate::bit_frame::FrameError::NotEnoughBits)
+                }
+                #(#sync_checks)*
+                Ok(Self { #(#decoded),* })
+            }
+
+            fn encode_into(&self, bits: &mut [crate::bitrep::Bit]) -> ::core::result::Result<(), #error_ty> {
+                if bits.len() != Self::LEN {
+                    return Err(crate::bit_frame::FrameError::NotEnoughBits)
+                }
+                bits.fill(crate::bitrep::Bit::Unknown);
+                #(#encoded)*
+                #(#encoded_parities)*
+                Ok(())
+            }
+        }
+    })
+}
@@ -0,0 +1,6 @@
+// Layouts the derive has to reject, with the errors in tests/ui/*.stderr
+#[test]
+fn test_rejected_layouts() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
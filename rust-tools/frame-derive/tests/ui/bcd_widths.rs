@@ -0,0 +1,10 @@
+use frame_derive::BitFrame;
+
+#[derive(BitFrame)]
+#[frame(len = 8)]
+struct Fields {
+    #[field(bits = 0..7, bcd = [4, 2], labels = ["min", "10m"])]
+    minute: i8
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use frame_derive::BitFrame;
+
+#[derive(BitFrame)]
+#[frame(len = 8)]
+struct Fields {
+    #[field(bits = 1..3, enumeration, labels = ["Z1", "Z2"], header = "DMZ")]
+    timezone: u8
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use frame_derive::BitFrame;
+
+#[derive(BitFrame)]
+#[frame(len = 8)]
+struct Fields {
+    #[field(bits = 0)]
+    start: bool
+}
+
+fn main() {}
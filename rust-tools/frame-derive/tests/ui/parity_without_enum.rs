@@ -0,0 +1,11 @@
+use frame_derive::BitFrame;
+
+#[derive(BitFrame)]
+#[frame(len = 8)]
+#[parity(name = "minute", bits = 0..8, label = "P1")]
+struct Fields {
+    #[field(bits = 0..7, bcd = [4, 3], labels = ["min", "10m"])]
+    minute: i8
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use frame_derive::BitFrame;
+
+#[derive(BitFrame)]
+#[frame(len = 9)]
+struct Fields {
+    #[field(bits = 0..9, skip = [2], bcd = [4, 4], labels = ["min", "10m"])]
+    minute: i8
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use frame_derive::BitFrame;
+
+#[derive(BitFrame)]
+#[frame(len = 36)]
+struct Fields {
+    #[field(bits = 0..36, bcd = [4, 4, 4, 4, 4, 4, 4, 4, 4], labels = ["1", "10", "100", "1k", "10k", "100k", "1M", "10M", "100M"])]
+    count: u64
+}
+
+fn main() {}
@@ -0,0 +1,309 @@
+use core::fmt::{self};
+use core::ops::Range;
+
+use crate::bitrep::*;
+use crate::frame_view::FieldSpan;
+
+pub use frame_derive::BitFrame;
+
+/* Frame layouts declared on a struct, one attribute per field:
+ *
+ *  #[derive(BitFrame)]
+ *  #[frame(len = 60, parity = ParityBitName)]
+ *  #[fixed(name = "minute_marker", bits = 59, label = "_", skipped)]
+ *  #[parity(name = "minute", bits = 21..29, label = "P1")]
+ *  struct Fields {
+ *      #[field(bits = 20, label = "S")]
+ *      start: bool,
+ *      #[field(bits = 21..28, bcd = [4, 3], labels = ["min", "10m"])]
+ *      minute: i8,
+ *      ...
+ *  }
+ *
+ * Fields are flags (bool), BCD (digit widths in the order sent), enums
+ * (`enumeration`, the type implements `BitEnum`), unsigned numbers
+ * (`binary`) or raw bits (`raw`, a `[Bit; N]` as long as the field).
+ * Digits, numbers and enum values are sent lsb first, unless marked `msb`;
+ * their fields have at most 32 bits.
+ * `skip` takes bits out of a field that belong to something else, e.g. the
+ * position markers within WWVB's day of year, as long as no BCD digit is
+ * split by them. `header` sets the characters of the header line, which
+ * defaults to the first letter of flags and the weights of BCD bits.
+ *
+ * Parity groups include the parity bit as their last bit and are even,
+ * unless marked `odd`. They are told apart by an enum generated under the
+ * name given as `parity`, one variant per group (`Minute` for "minute").
+ * `fixed` bits are markers that carry no data; `skipped` ones must be
+ * missing for the frame to be in sync. Their bits can also be given as a
+ * list, `bits = [4, 10..12]`, the constant then lists the positions.
+ *
+ * Deriving implements `BitFrame` and adds, for each field, fixed bit and
+ * parity group, the constants `<NAME>_BITS` / `<NAME>_PARITY_BITS` and the
+ * functions `decode_<field>` / `check_<name>_parity`. Field decoders check
+ * the parity covering the field first, so they can be used on their own for
+ * partial decoding.
+ */
+pub trait BitFrame: Sized {
+    // The generated parity enum, `Infallible` without parity groups
+    type Parity: Copy + fmt::Debug;
+
+    const LEN: usize;
+
+    // One character per bit: field letters, digit weights, P for parity
+    const HEADER: &'static str;
+
+    const LAYOUT: &'static [FieldSpan];
+
+    fn decode_bits(bits: &[Bit]) -> Result<Self, FrameError<Self::Parity>>;
+
+    // Bits not belonging to any field are unknown
+    fn encode_into(&self, bits: &mut [Bit]) -> Result<(), FrameError<Self::Parity>>;
+
+    #[cfg(feature = "std")]
+    fn encode_bits(&self) -> Result<Vec<Bit>, FrameError<Self::Parity>> {
+        let mut bits = vec![Bit::Unknown; Self::LEN];
+        self.encode_into(&mut bits)?;
+        Ok(bits)
+    }
+}
+
+// Enumerations sent as a small number
+pub trait BitEnum: Sized {
+    fn from_bit_value(value: u32) -> Option<Self>;
+    fn to_bit_value(&self) -> u32;
+}
+
+// Fields by the names in the layout, parity groups by `BitFrame::Parity`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameError<P> {
+    NotEnoughBits,
+    NotSync,
+    MissingBit(usize),
+    ParityError(P),
+    InvalidDigit(&'static str, u8),
+    InvalidValue(&'static str, u32)
+}
+
+impl<P: fmt::Display> fmt::Display for FrameError<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::NotEnoughBits         => write!(f, "not enough bits for a full frame"),
+            Self::NotSync               => write!(f, "frame marker out of place"),
+            Self::MissingBit(n)         => write!(f, "bit {} is missing", n),
+            Self::ParityError(ref id)   => write!(f, "{} parity error", id),
+            Self::InvalidDigit(name, v) => write!(f, "invalid {} digit {}", name, v),
+            Self::InvalidValue(name, v) => write!(f, "invalid {} {}", name, v),
+        }
+    }
+}
+
+impl<P: fmt::Debug + fmt::Display> core::error::Error for FrameError<P> {}
+
+// Bit ranges in failure messages, printed inclusive like in the layout tables
+pub struct BitRange(pub Range<usize>);
+
+impl fmt::Display for BitRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.len() == 1 {
+            write!(f, "bit {}", self.0.start)
+        } else {
+            write!(f, "bits {}-{}", self.0.start, self.0.end - 1)
+        }
+    }
+}
+
+/***************************************************************************************************
+ * Support for the generated code
+ */
+
+// Fields have at most 32 bits, see `field_bits`
+const MAX_FIELD_BITS: usize = 32;
+
+fn missing_bit<P>(bits: &[Bit], positions: impl Iterator<Item = usize> + Clone) -> FrameError<P> {
+    let first = positions.clone().next().unwrap_or(0);
+    let n = positions
+        .into_iter()
+        .find(|n| bits[*n].to_bit().is_none())
+        .unwrap_or(first);
+    FrameError::MissingBit(n)
+}
+
+// The bits of a field, which need not be contiguous in the frame
+fn field_bits(bits: &[Bit], positions: &[usize]) -> [Bit; MAX_FIELD_BITS] {
+    let mut field = [Bit::Unknown; MAX_FIELD_BITS];
+    for (bit, n) in field.iter_mut().zip(positions) {
+        *bit = bits[*n];
+    }
+    field
+}
+
+pub fn check_parity<P>(id: P, bits: &[Bit], range: Range<usize>, odd: bool) -> Result<(), FrameError<P>> {
+    let mut parity = odd;
+    for bit in &bits[range.clone()] {
+        parity ^= bit.to_bit().ok_or_else(|| missing_bit(bits, range.clone()))?;
+    }
+    if parity { Err(FrameError::ParityError(id)) } else { Ok(()) }
+}
+
+// Sets the last bit of the group, unknown if any of the others is
+pub fn encode_parity(bits: &mut [Bit], range: Range<usize>, odd: bool) {
+    let parity_bit = range.end - 1;
+    let parity = bits[range.start..parity_bit].iter()
+        .try_fold(odd, |parity, bit| bit.to_bit().map(|value| parity ^ value));
+    bits[parity_bit] = parity.map_or(Bit::Unknown, Bit::from);
+}
+
+pub fn decode_flag<P>(bits: &[Bit], n: usize) -> Result<bool, FrameError<P>> {
+    bits[n].to_bit().ok_or(FrameError::MissingBit(n))
+}
+
+pub fn decode_bcd<P>(name: &'static str, bits: &[Bit], positions: &[usize], bcd: Bcd) -> Result<u32, FrameError<P>> {
+    bcd.decode(&field_bits(bits, positions)[..positions.len()]).map_err(|e| match e {
+        BcdError::MissingBit(n) => FrameError::MissingBit(positions[n]),
+        BcdError::InvalidDigit { value, .. } => FrameError::InvalidDigit(name, value),
+        BcdError::Overflow(value) => FrameError::InvalidValue(name, value),
+    })
+}
+
+pub fn encode_bcd<P>(name: &'static str, bits: &mut [Bit], positions: &[usize], bcd: Bcd, value: u32) -> Result<(), FrameError<P>> {
+    let mut field = [Bit::Unknown; MAX_FIELD_BITS];
+    bcd.encode_into(value, &mut field[..positions.len()]).map_err(|_| FrameError::InvalidValue(name, value))?;
+    for (bit, n) in field.iter().zip(positions) {
+        bits[*n] = *bit;
+    }
+    Ok(())
+}
+
+pub fn decode_binary<P>(bits: &[Bit], positions: &[usize], msb: bool) -> Result<u32, FrameError<P>> {
+    let field = &field_bits(bits, positions)[..positions.len()];
+    let value: Option<u32> = if msb { FromBits::from_maybebits_msb(field) } else { FromBits::from_maybebits_lsb(field) };
+    value.ok_or_else(|| missing_bit(bits, positions.iter().copied()))
+}
+
+pub fn encode_binary<P>(name: &'static str, bits: &mut [Bit], positions: &[usize], msb: bool, value: u32) -> Result<(), FrameError<P>> {
+    let width = positions.len();
+    if width < 32 && value >> width != 0 {
+        return Err(FrameError::InvalidValue(name, value))
+    }
+    for (i, n) in positions.iter().enumerate() {
+        let shift = if msb { width - 1 - i } else { i };
+        bits[*n] = Bit::from(value >> shift & 1 != 0);
+    }
+    Ok(())
+}
+
+pub fn decode_enum<T: BitEnum, P>(name: &'static str, bits: &[Bit], positions: &[usize], msb: bool) -> Result<T, FrameError<P>> {
+    let value = decode_binary(bits, positions, msb)?;
+    T::from_bit_value(value).ok_or(FrameError::InvalidValue(name, value))
+}
+
+pub fn encode_enum<T: BitEnum, P>(name: &'static str, bits: &mut [Bit], positions: &[usize], msb: bool, value: &T) -> Result<(), FrameError<P>> {
+    encode_binary(name, bits, positions, msb, value.to_bit_value())
+}
+
+pub fn decode_raw<const N: usize>(bits: &[Bit], positions: &[usize]) -> [Bit; N] {
+    assert_eq!(positions.len(), N, "raw field length");
+    core::array::from_fn(|i| bits[positions[i]])
+}
+
+pub fn encode_raw<P>(name: &'static str, bits: &mut [Bit], positions: &[usize], value: &[Bit]) -> Result<(), FrameError<P>> {
+    if value.len() != positions.len() {
+        return Err(FrameError::InvalidValue(name, value.len() as u32))
+    }
+    for (bit, n) in value.iter().zip(positions) {
+        bits[*n] = *bit;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    enum Mode {
+        Idle,
+        Busy
+    }
+
+    impl BitEnum for Mode {
+        fn from_bit_value(value: u32) -> Option<Self> {
+            match value {
+                0b01 => Some(Self::Idle),
+                0b10 => Some(Self::Busy),
+                _ => None
+            }
+        }
+
+        fn to_bit_value(&self) -> u32 {
+            match *self {
+                Self::Idle => 0b01,
+                Self::Busy => 0b10,
+            }
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq, BitFrame)]
+    #[frame(len = 12, parity = ExampleParity)]
+    #[fixed(name = "sync", bits = 11, label = "_", skipped)]
+    #[parity(name = "value", bits = 3..11, label = "P", odd)]
+    struct Example {
+        #[field(bits = 0, label = "F")]
+        flag: bool,
+        #[field(bits = 1..3, enumeration, labels = ["M1", "M2"], header = "MM")]
+        mode: Mode,
+        #[field(bits = 3..10, bcd = [3, 4], msb, labels = ["10v", "v"])]
+        value: u8
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_derived_frame() {
+        assert_eq!(Example::HEADER, "FMM4218421P_");
+        assert_eq!(Example::LAYOUT.iter().map(|span| span.label).collect::<Vec<_>>(),
+            ["F", "M1", "M2", "10v", "v", "P", "_"]);
+        assert_eq!(Example::VALUE_BITS, 3..10);
+
+        let example = Example { flag: true, mode: Mode::Busy, value: 42 };
+        let bits = example.encode_bits().expect("Could not encode");
+        assert_eq!(BitString(bits.clone()).to_string(), "10110000101_");
+        assert_eq!(Example::decode_bits(&bits), Ok(example.clone()));
+
+        let mut broken = bits.clone();
+        broken[9] = Bit::Value(true);
+        assert_eq!(Example::decode_value(&broken), Err(FrameError::ParityError(ExampleParity::Value)));
+        assert_eq!(ExampleParity::Value.bit_range(), 3..11);
+        broken[1] = Bit::Value(true);
+        assert_eq!(Example::decode_mode(&broken), Err(FrameError::InvalidValue("mode", 3)));
+        broken[11] = Bit::Value(false);
+        assert_eq!(Example::decode_bits(&broken), Err(FrameError::NotSync));
+        assert_eq!(Example { value: 100, ..example }.encode_bits(), Err(FrameError::InvalidValue("value", 100)));
+    }
+
+    #[derive(Clone, Debug, PartialEq, BitFrame)]
+    #[frame(len = 10)]
+    #[fixed(name = "markers", bits = [0, 4], label = "M", skipped)]
+    struct Split {
+        #[field(bits = 1..8, skip = [4], bcd = [3, 3], msb, labels = ["10v", "v"])]
+        value: u8,
+        #[field(bits = 8..10, binary, label = "n")]
+        count: u8
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_skipped_bits() {
+        assert_eq!(Split::MARKERS_BITS, &[0, 4]);
+        assert_eq!(Split::LAYOUT.iter().map(|span| (span.label, span.bits.clone())).collect::<Vec<_>>(),
+            [("M", 0..1), ("10v", 1..4), ("M", 4..5), ("v", 5..8), ("n", 8..10)]);
+
+        let split = Split { value: 57, count: 2 };
+        let mut bits = split.encode_bits().expect("Could not encode");
+        assert_eq!(BitString(bits.clone()).to_string(), "_101_11101");
+        assert_eq!(Split::decode_bits(&bits), Ok(split.clone()));
+
+        bits[5] = Bit::Unknown;
+        assert_eq!(Split::decode_value(&bits), Err(FrameError::MissingBit(5)));
+        assert_eq!(Split { count: 4, ..split }.encode_bits(), Err(FrameError::InvalidValue("count", 4)));
+    }
+}
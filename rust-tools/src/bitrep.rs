@@ -1,4 +1,5 @@
-use std::fmt::{self};
+use core::fmt::{self};
+use core::ops::Range;
 
 /* Notes:
     * Maybe should be something like an iterator.
@@ -63,6 +64,7 @@ pub trait MaybeBits {
     fn bit_len(&self) -> usize;
     fn nth_bit(&self, n: usize) -> Self::BitElem;
 
+    #[cfg(feature = "std")]
     fn to_bit_vector(&self) -> Vec<Self::BitElem> {
         let mut result = Vec::new();
         for i in 0..self.bit_len() {
@@ -72,20 +74,11 @@ pub trait MaybeBits {
     }
 }
 
-/*
+// Bit sequences whose length is known at compile time
+#[allow(unused)]
 pub trait FixedLengthMaybeBits : MaybeBits {
     const BIT_COUNT: usize;
 }
-*/
-
-/*
-impl<PB: FixedLengthMaybeBits> MaybeBits for PB {
-    // Cannot implement partially :(
-    fn bit_len(&self) -> usize {
-        Self::BIT_COUNT
-    }
-}
-*/
 
 #[allow(unused)]
 pub trait FromBits : Sized {
@@ -101,12 +94,17 @@ pub trait FromBits : Sized {
         Self::from_bits_iter(iter.into_iter().rev())
     }
 
+    /* Unknown bits are read as zeros and spoil the result afterwards, so
+        bits not consumed by `from_bits_iter` (e.g. beyond the length of an
+        array) are not checked.
+     */
     fn from_maybebits_iter<B: MaybeBit, T: Iterator<Item = B>>(iter: T) -> Option<Self> {
-        // FIXME: can we get by without an intermediate Vec<> ?
-        let x: Option<Vec<_>> = iter
-            .map(|b| b.to_bit())
-            .collect();
-        x.map(|vec| Self::from_bits_iter(vec.iter()))
+        let mut complete = true;
+        let value = Self::from_bits_iter(iter.map(|b| b.to_bit().unwrap_or_else(|| {
+            complete = false;
+            false
+        })));
+        complete.then_some(value)
     }
 
     fn from_maybebits_msb<B: MaybeBit, T: IntoIterator<Item = B>>(iter: T) -> Option<Self> {
@@ -126,167 +124,177 @@ pub trait FromBits : Sized {
 
 impl PureBit for bool {
     fn to_bool(&self) -> bool {
-        return *self;
+        *self
     }
 }
 
-impl MaybeBits for u8 {
-    type BitElem = bool;
-    fn bit_len(&self) -> usize {
-        8
-    }
-    fn nth_bit(&self, n: usize) -> Self::BitElem {
-        let x = *self & (0x1 << n);
-        x != 0
-    }
+/* Integers: bit n is the bit with value 2^n, and the bits to build an
+    integer from are given msb first.
+ */
+macro_rules! impl_int_bits {
+    ($($t:ty)*) => {$(
+        impl MaybeBits for $t {
+            type BitElem = bool;
+            fn bit_len(&self) -> usize {
+                <$t>::BITS as usize
+            }
+            fn nth_bit(&self, n: usize) -> Self::BitElem {
+                (*self >> n) & 1 != 0
+            }
+        }
+
+        impl FixedLengthMaybeBits for $t {
+            const BIT_COUNT: usize = <$t>::BITS as usize;
+        }
+
+        impl FromBits for $t {
+            fn from_bits_iter<B: PureBit, T: Iterator<Item = B>>(iter: T) -> Self {
+                let mut i: $t = 0;
+                for bit in iter {
+                    i = (i << 1) | <$t>::from(bit.to_bool());
+                }
+                i
+            }
+        }
+    )*}
 }
 
-impl MaybeBits for u16 {
-    type BitElem = bool;
+impl_int_bits!(u8 u16 u32 u64 u128 usize i8 i16 i32 i64 i128 isize);
+
+/* Containers: bit n is element n. Building one from bits keeps their order,
+    so `from_bits_msb` gives the bits as they are and `from_bits_lsb`
+    reversed. Arrays are padded with zeros or cut off to their length.
+ */
+impl<B: MaybeBit + Copy> MaybeBits for [B] {
+    type BitElem = B;
     fn bit_len(&self) -> usize {
-        16
+        self.len()
     }
     fn nth_bit(&self, n: usize) -> Self::BitElem {
-        let x = *self & (0x1 << n);
-        x != 0
+        self[n]
     }
 }
 
-impl MaybeBits for u32 {
-    type BitElem = bool;
+impl<B: MaybeBit + Copy, const N: usize> MaybeBits for [B; N] {
+    type BitElem = B;
     fn bit_len(&self) -> usize {
-        32
+        N
     }
     fn nth_bit(&self, n: usize) -> Self::BitElem {
-        let x = *self & (0x1 << n);
-        x != 0
+        self[n]
     }
 }
 
-impl MaybeBits for i8 {
-    type BitElem = bool;
-    fn bit_len(&self) -> usize {
-        8
-    }
-    fn nth_bit(&self, n: usize) -> Self::BitElem {
-        let x = *self & (0x1 << n);
-        x != 0
-    }
+impl<B: MaybeBit + Copy, const N: usize> FixedLengthMaybeBits for [B; N] {
+    const BIT_COUNT: usize = N;
 }
 
-impl MaybeBits for i16 {
-    type BitElem = bool;
+#[cfg(feature = "std")]
+impl<B: MaybeBit + Copy> MaybeBits for Vec<B> {
+    type BitElem = B;
     fn bit_len(&self) -> usize {
-        16
+        self.len()
     }
     fn nth_bit(&self, n: usize) -> Self::BitElem {
-        let x = *self & (0x1 << n);
-        x != 0
+        self[n]
     }
 }
 
-impl MaybeBits for i32 {
-    type BitElem = bool;
+impl<M: MaybeBits + ?Sized> MaybeBits for &M {
+    type BitElem = M::BitElem;
     fn bit_len(&self) -> usize {
-        32
+        (*self).bit_len()
     }
     fn nth_bit(&self, n: usize) -> Self::BitElem {
-        let x = *self & (0x1 << n);
-        x != 0
+        (*self).nth_bit(n)
     }
 }
 
-/*
-impl FixedLengthMaybeBits for u8 {
-    const BIT_COUNT: usize = 8;
+impl<B: From<bool>, const N: usize> FromBits for [B; N] {
+    fn from_bits_iter<PB: PureBit, T: Iterator<Item = PB>>(mut iter: T) -> Self {
+        core::array::from_fn(|_| B::from(iter.next().is_some_and(|b| b.to_bool())))
+    }
 }
-*/
 
-impl FromBits for u8 {
-    fn from_bits_iter<B: PureBit, T: Iterator<Item = B>>(iter: T) -> Self {
-        let mut i: u8 = 0;
-        for bit in iter {
-            i <<= 1;
-            i += if bit.to_bool() { 1 } else { 0 };
-        }
-        i
+#[cfg(feature = "std")]
+impl<B: From<bool>> FromBits for Vec<B> {
+    fn from_bits_iter<PB: PureBit, T: Iterator<Item = PB>>(iter: T) -> Self {
+        iter.map(|b| B::from(b.to_bool())).collect()
     }
 }
 
-impl FromBits for u16 {
-    fn from_bits_iter<B: PureBit, T: Iterator<Item = B>>(iter: T) -> Self {
-        let mut i: u16 = 0;
-        for bit in iter {
-            i <<= 1;
-            i += if bit.to_bool() { 1 } else { 0 };
-        }
-        i
-    }
+/***************************************************************************************************
+ * "Bit" for signal decoding
+ */
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bit {
+    Unknown,
+    Skipped,
+    Value(bool)
 }
 
-impl FromBits for u32 {
-    fn from_bits_iter<B: PureBit, T: Iterator<Item = B>>(iter: T) -> Self {
-        let mut i: u32 = 0;
-        for bit in iter {
-            i <<= 1;
-            i += if bit.to_bool() { 1 } else { 0 };
+impl Bit {
+    pub const fn to_char(self) -> char {
+        match self {
+            Bit::Unknown        => '?',
+            Bit::Skipped        => '_',
+            Bit::Value(false)   => '0',
+            Bit::Value(true)    => '1',
         }
-        i
     }
-}
 
-impl FromBits for i8 {
-    fn from_bits_iter<B: PureBit, T: Iterator<Item = B>>(iter: T) -> Self {
-        let mut i: i8 = 0;
-        for bit in iter {
-            i <<= 1;
-            i += if bit.to_bool() { 1 } else { 0 };
+    pub const fn from_char(c: char) -> Option<Bit> {
+        match c {
+            '0' => Some(Bit::Value(false)),
+            '1' => Some(Bit::Value(true)),
+            '?' => Some(Bit::Unknown),
+            '_' => Some(Bit::Skipped),
+            _ => None
         }
-        i
     }
 }
 
-impl FromBits for i16 {
-    fn from_bits_iter<B: PureBit, T: Iterator<Item = B>>(iter: T) -> Self {
-        let mut i: i16 = 0;
-        for bit in iter {
-            i <<= 1;
-            i += if bit.to_bool() { 1 } else { 0 };
-        }
-        i
+impl fmt::Display for Bit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_char())
     }
 }
 
-impl FromBits for i32 {
-    fn from_bits_iter<B: PureBit, T: Iterator<Item = B>>(iter: T) -> Self {
-        let mut i: i32 = 0;
-        for bit in iter {
-            i <<= 1;
-            i += if bit.to_bool() { 1 } else { 0 };
-        }
-        i
+// Like in recordings, a single character
+#[cfg(feature = "serde")]
+impl serde::Serialize for Bit {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_char(self.to_char())
     }
 }
 
-/***************************************************************************************************
- * "Bit" for signal decoding
- */
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum Bit {
-    Unknown,
-    Skipped,
-    Value(bool)
-}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Bit {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BitVisitor;
+
+        impl serde::de::Visitor<'_> for BitVisitor {
+            type Value = Bit;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "one of the characters 0, 1, ? or _")
+            }
+
+            fn visit_char<E: serde::de::Error>(self, c: char) -> Result<Bit, E> {
+                Bit::from_char(c).ok_or_else(|| E::invalid_value(serde::de::Unexpected::Char(c), &self))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Bit, E> {
+                let mut chars = s.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => self.visit_char(c),
+                    _ => Err(E::invalid_value(serde::de::Unexpected::Str(s), &self))
+                }
+            }
+        }
 
-impl fmt::Display for Bit {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", match *self {
-            Bit::Unknown        => "?",
-            Bit::Skipped        => "_",
-            Bit::Value(false)   => "0",
-            Bit::Value(true)    => "1",
-        })
+        deserializer.deserialize_char(BitVisitor)
     }
 }
 
@@ -299,6 +307,12 @@ impl MaybeBit for Bit {
     }
 }
 
+impl From<bool> for Bit {
+    fn from(value: bool) -> Self {
+        Bit::Value(value)
+    }
+}
+
 impl MaybeBit for &Bit {
     fn to_bit(&self) -> Option<bool> {
         match *self {
@@ -315,12 +329,259 @@ pub fn to_bit(b: impl MaybeBit) -> Bit {
     }
 }
 
+/***************************************************************************************************
+ * BCD codec
+ *
+ * A BCD field is a group of digits, each given by its width in bits, in the
+ * order they are sent. The time codes send them either way round:
+ *      LsbFirst    least significant digit first, each digit lsb first (DCF77, IRIG)
+ *      MsbFirst    most significant digit first, each digit msb first (MSF, WWVB, JJY)
+ */
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BcdOrder {
+    LsbFirst,
+    MsbFirst
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bcd {
+    pub widths: &'static [usize],
+    pub order: BcdOrder
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BcdError {
+    // Index of the first missing bit in the field
+    MissingBit(usize),
+    // A digit above 9: the index of its first bit in the field, and its value
+    InvalidDigit { bit: usize, value: u8 },
+    // The value has more digits, or larger ones, than the field can take
+    Overflow(u32)
+}
+
+impl fmt::Display for BcdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::MissingBit(n) => write!(f, "bit {} is missing", n),
+            Self::InvalidDigit { bit, value } => write!(f, "invalid BCD digit {} at bit {}", value, bit),
+            Self::Overflow(value) => write!(f, "{} does not fit the BCD field", value),
+        }
+    }
+}
+
+impl core::error::Error for BcdError {}
+
+impl Bcd {
+    pub const fn lsb_first(widths: &'static [usize]) -> Bcd {
+        Bcd { widths, order: BcdOrder::LsbFirst }
+    }
+
+    pub const fn msb_first(widths: &'static [usize]) -> Bcd {
+        Bcd { widths, order: BcdOrder::MsbFirst }
+    }
+
+    // Number of bits in the field
+    pub fn len(&self) -> usize {
+        self.widths.iter().sum()
+    }
+
+    // The bits of each digit within the field, in the order they are sent
+    pub fn digits(&self) -> impl Iterator<Item = Range<usize>> + '_ {
+        self.widths.iter().scan(0, |start, width| {
+            let range = *start..*start + width;
+            *start += width;
+            Some(range)
+        })
+    }
+
+    pub fn decode(&self, bits: &[Bit]) -> Result<u32, BcdError> {
+        assert_eq!(bits.len(), self.len());
+        let mut value: u32 = 0;
+        let mut scale: u32 = 1;
+        for range in self.digits() {
+            let digit_bits = &bits[range.clone()];
+            let digit: Option<u8> = match self.order {
+                BcdOrder::LsbFirst => FromBits::from_maybebits_lsb(digit_bits),
+                BcdOrder::MsbFirst => FromBits::from_maybebits_msb(digit_bits),
+            };
+            let digit = digit.ok_or_else(|| {
+                let n = range.clone().find(|n| bits[*n].to_bit().is_none()).unwrap_or(range.start);
+                BcdError::MissingBit(n)
+            })?;
+            if digit > 9 {
+                return Err(BcdError::InvalidDigit { bit: range.start, value: digit })
+            }
+            match self.order {
+                BcdOrder::LsbFirst => {
+                    value += digit as u32 * scale;
+                    scale *= 10;
+                }
+                BcdOrder::MsbFirst => value = value * 10 + digit as u32,
+            }
+        }
+        Ok(value)
+    }
+
+    // `bits` takes the whole field
+    pub fn encode_into(&self, value: u32, bits: &mut [Bit]) -> Result<(), BcdError> {
+        assert_eq!(bits.len(), self.len());
+        let digit_count = self.widths.len() as u32;
+        if 10u32.checked_pow(digit_count).is_some_and(|limit| value >= limit) {
+            return Err(BcdError::Overflow(value))
+        }
+        for (i, range) in self.digits().enumerate() {
+            let power = match self.order {
+                BcdOrder::LsbFirst => i as u32,
+                BcdOrder::MsbFirst => digit_count - 1 - i as u32,
+            };
+            let digit = value / 10u32.pow(power) % 10;
+            let width = range.len();
+            if digit >> width != 0 {
+                return Err(BcdError::Overflow(value))
+            }
+            for (j, n) in range.enumerate() {
+                let shift = match self.order {
+                    BcdOrder::LsbFirst => j,
+                    BcdOrder::MsbFirst => width - 1 - j,
+                };
+                bits[n] = Bit::from(digit >> shift & 1 != 0);
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "std")]
+    pub fn encode(&self, value: u32) -> Result<Vec<Bit>, BcdError> {
+        let mut bits = vec![Bit::Unknown; self.len()];
+        self.encode_into(value, &mut bits)?;
+        Ok(bits)
+    }
+}
+
+/***************************************************************************************************
+ * Bit strings, the text format for bits in logs, tests and on the command line:
+ *      0, 1    bit values
+ *      ?       unknown bit
+ *      _       skipped bit (no pulse)
+ * Whitespace, '|', ',' and '.' are ignored when parsing.
+ */
+
+#[cfg(feature = "std")]
+pub use bit_string::*;
+
+#[cfg(feature = "std")]
+mod bit_string {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[derive(Clone, Debug, Default, PartialEq, Eq)]
+    pub struct BitString(pub Vec<Bit>);
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct ParseBitStringError {
+        // Character position in the input
+        pub position: usize,
+        pub found: char
+    }
+
+    impl std::error::Error for ParseBitStringError {}
+
+    impl fmt::Display for ParseBitStringError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "invalid bit '{}' at position {}", self.found, self.position)
+        }
+    }
+
+    impl FromStr for BitString {
+        type Err = ParseBitStringError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            s.chars().enumerate()
+                .filter(|(_, c)| !(c.is_whitespace() || "|,.".contains(*c)))
+                .map(|(position, c)| Bit::from_char(c)
+                    .ok_or(ParseBitStringError { position, found: c }))
+                .collect::<Result<Vec<Bit>, _>>()
+                .map(BitString)
+        }
+    }
+
+    impl BitString {
+        pub fn bits(&self) -> &[Bit] {
+            &self.0
+        }
+
+        // Formatting with a space after every `size` bits
+        pub fn grouped(&self, size: usize) -> Grouped<'_> {
+            Grouped { bits: &self.0, size }
+        }
+    }
+
+    impl From<Vec<Bit>> for BitString {
+        fn from(value: Vec<Bit>) -> Self {
+            BitString(value)
+        }
+    }
+
+    impl From<&[Bit]> for BitString {
+        fn from(value: &[Bit]) -> Self {
+            BitString(value.to_vec())
+        }
+    }
+
+    impl FromIterator<Bit> for BitString {
+        fn from_iter<T: IntoIterator<Item = Bit>>(iter: T) -> Self {
+            BitString(iter.into_iter().collect())
+        }
+    }
+
+    /* Width, fill and alignment (e.g. `{:>60}`) apply to the whole string,
+        which the derived style of writing bit by bit can't do.
+     */
+    impl fmt::Display for BitString {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.pad(&self.0.iter().map(|b| b.to_char()).collect::<String>())
+        }
+    }
+
+    // As a string of bit characters, e.g. "0110_"
+    #[cfg(feature = "serde")]
+    impl serde::Serialize for BitString {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    impl<'de> serde::Deserialize<'de> for BitString {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let s = String::deserialize(deserializer)?;
+            s.parse().map_err(serde::de::Error::custom)
+        }
+    }
+
+    pub struct Grouped<'a> {
+        bits: &'a [Bit],
+        size: usize
+    }
+
+    impl fmt::Display for Grouped<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let groups: Vec<String> = self.bits.chunks(self.size.max(1))
+                .map(|group| group.iter().map(|b| b.to_string()).collect())
+                .collect();
+            f.pad(&groups.join(" "))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
 
-        #[test]
+    #[test]
     fn test_frombits_i8() {
         let mut i: i8 = FromBits::from_bits_msb([true, false]);
         assert_eq!(i, 2);
@@ -335,4 +596,57 @@ mod tests {
         i = FromBits::from_bits_msb([true, false, true]);
         assert_eq!(i, 5);
     }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_wide_and_containers() {
+        let wide: u128 = 1 << 100 | 1;
+        let bits = wide.to_bit_vector();
+        assert_eq!((bits.len(), bits[100], bits[99]), (128, true, false));
+        assert_eq!(u128::from_bits_lsb(bits.iter().copied()), wide);
+        assert_eq!(u64::from_bits_lsb(0xDEAD_BEEF_u64.to_bit_vector()), 0xDEAD_BEEF);
+        assert_eq!(<usize as FixedLengthMaybeBits>::BIT_COUNT, usize::BITS as usize);
+
+        let frame: [Bit; 4] = FromBits::from_bits_msb([true, false, true]);
+        assert_eq!(frame, [Bit::Value(true), Bit::Value(false), Bit::Value(true), Bit::Value(false)]);
+        assert_eq!(<[Bit; 100] as FixedLengthMaybeBits>::BIT_COUNT, 100);
+        let slice: &[Bit] = &frame[1..];
+        assert_eq!(slice.bit_len(), 3);
+        let v: Option<Vec<Bit>> = FromBits::from_maybebits_lsb(slice);
+        assert_eq!(v, Some(vec![Bit::Value(false), Bit::Value(true), Bit::Value(false)]));
+        assert_eq!(u8::from_maybebits_msb(vec![Bit::Value(true), Bit::Unknown]), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_bitstring() {
+        let bits: BitString = "01?_ | 10,1.1".parse().expect("Could not parse bits");
+        assert_eq!(bits.bits()[..4], [Bit::Value(false), Bit::Value(true), Bit::Unknown, Bit::Skipped]);
+        assert_eq!(bits.to_string(), "01?_1011");
+        assert_eq!(format!("{:>10}", bits), "  01?_1011");
+        assert_eq!(format!("[{:<12}]", bits.grouped(3)), "[01? _10 11  ]");
+        assert_eq!("0 1 2".parse::<BitString>(), Err(ParseBitStringError { position: 4, found: '2' }));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_bcd() {
+        // DCF77 year 24, IRIG style: units first, lsb first
+        let year = Bcd::lsb_first(&[4, 4]);
+        let bits: BitString = "0010 0100".parse().unwrap();
+        assert_eq!(year.decode(bits.bits()), Ok(24));
+        assert_eq!(year.encode(24), Ok(bits.0));
+        // WWVB day of year 186: hundreds first, msb first
+        let day = Bcd::msb_first(&[2, 4, 4]);
+        let bits: BitString = "01 1000 0110".parse().unwrap();
+        assert_eq!(day.decode(bits.bits()), Ok(186));
+        assert_eq!(day.encode(186), Ok(bits.0));
+        assert_eq!(day.encode(400), Err(BcdError::Overflow(400)));
+        assert_eq!(day.encode(1000), Err(BcdError::Overflow(1000)));
+
+        let nibble_14: BitString = "0111 0000".parse().unwrap();
+        assert_eq!(year.decode(nibble_14.bits()), Err(BcdError::InvalidDigit { bit: 0, value: 14 }));
+        let unknown: BitString = "0010 01?0".parse().unwrap();
+        assert_eq!(year.decode(unknown.bits()), Err(BcdError::MissingBit(6)));
+    }
 }
@@ -0,0 +1,143 @@
+use jiff::civil::{Date, Weekday};
+
+/* Time codes only transmit two digit years. How we choose the century:
+ *
+ * Pivot(base)          The year lies in base..base+100, e.g. Pivot(2000)
+ *                      just adds 2000 (the default), Pivot(1970) maps
+ *                      70-99 to 19xx and 00-69 to 20xx.
+ * Weekday(reference)   Like commercial receivers, use the transmitted day
+ *                      of week: the weekday of a given date repeats only
+ *                      every 400 years, so among the four candidate
+ *                      centuries around the reference date at most one
+ *                      matches. Codes without a day of week (WWVB) take
+ *                      the year closest to the reference date instead.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CenturyPolicy {
+    Pivot(i16),
+    Weekday(Date)
+}
+
+impl Default for CenturyPolicy {
+    fn default() -> Self {
+        CenturyPolicy::Pivot(2000)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CenturyInference {
+    Configured,
+    Unique,
+    /* No or more than one candidate matched the weekday,
+        we fell back to the candidate closest to the reference date.
+     */
+    Ambiguous { matches: u8 },
+    // `Weekday` was configured, but the code sends no day of week
+    NoWeekday
+}
+
+impl CenturyInference {
+    pub fn is_ambiguous(&self) -> bool {
+        matches!(self, Self::Ambiguous { .. })
+    }
+}
+
+// Candidates are taken from 200 years before to 200 years after the reference
+const WINDOW_YEARS: i16 = 200;
+
+impl CenturyPolicy {
+    pub fn full_year(&self, year: i8, month: i8, day: i8, weekday: Option<Weekday>) -> (i16, CenturyInference) {
+        self.full_year_of(year, weekday, |y| Date::new(y, month, day).ok())
+    }
+
+    // For codes sending the day of the year (JJY)
+    pub fn full_year_of_day(&self, year: i8, day_of_year: i16, weekday: Option<Weekday>) -> (i16, CenturyInference) {
+        self.full_year_of(year, weekday, |y| Date::new(y, 1, 1).ok()?.with().day_of_year(day_of_year).build().ok())
+    }
+
+    // For codes without a day of week, `Weekday` falls back to a pivot around its reference date
+    pub fn full_year_without_weekday(&self, year: i8) -> (i16, CenturyInference) {
+        match *self {
+            Self::Pivot(_) => self.full_year(year, 1, 1, None),
+            Self::Weekday(reference) => {
+                let (full_year, _) = Self::Pivot(reference.year() - 50).full_year(year, 1, 1, None);
+                (full_year, CenturyInference::NoWeekday)
+            }
+        }
+    }
+
+    fn full_year_of(&self, year: i8, weekday: Option<Weekday>, date: impl Fn(i16) -> Option<Date>) -> (i16, CenturyInference) {
+        let year = i16::from(year);
+        match *self {
+            Self::Pivot(base) => {
+                let offset = (year - base).rem_euclid(100);
+                (base + offset, CenturyInference::Configured)
+            }
+            Self::Weekday(reference) => {
+                let first = reference.year() - WINDOW_YEARS;
+                let candidates = (0..(2 * WINDOW_YEARS / 100))
+                    .map(|i| first + (year - first).rem_euclid(100) + 100 * i);
+                let distance = |y: &i16| (y - reference.year()).abs();
+
+                let matches = |y: &i16| date(*y)
+                    .is_some_and(|date| Some(date.weekday()) == weekday);
+                match candidates.clone().filter(matches).count() {
+                    1 => (candidates.clone().find(matches).unwrap(), CenturyInference::Unique),
+                    count => {
+                        let nearest = candidates
+                            .filter(|y| count == 0 || matches(y))
+                            .min_by_key(distance)
+                            .unwrap_or(year);
+                        (nearest, CenturyInference::Ambiguous { matches: count as u8 })
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jiff::civil::date;
+    use super::*;
+
+    #[test]
+    fn test_pivot() {
+        assert_eq!(CenturyPolicy::default().full_year(16, 1, 1, None),
+            (2016, CenturyInference::Configured));
+        assert_eq!(CenturyPolicy::Pivot(1970).full_year(99, 1, 1, None),
+            (1999, CenturyInference::Configured));
+        assert_eq!(CenturyPolicy::Pivot(1970).full_year(69, 1, 1, None),
+            (2069, CenturyInference::Configured));
+    }
+
+    #[test]
+    fn test_weekday() {
+        let policy = CenturyPolicy::Weekday(date(2026, 1, 10));
+
+        // 1999-12-31 was a Friday, 2099-12-31 is a Thursday
+        assert_eq!(policy.full_year(99, 12, 31, Some(Weekday::Friday)),
+            (1999, CenturyInference::Unique));
+        assert_eq!(policy.full_year(99, 12, 31, Some(Weekday::Thursday)),
+            (2099, CenturyInference::Unique));
+        // 1916-01-01 was a Saturday
+        assert_eq!(policy.full_year(16, 1, 1, Some(Weekday::Saturday)),
+            (1916, CenturyInference::Unique));
+
+        // No century matches: fall back to the closest one
+        assert_eq!(policy.full_year(16, 1, 1, None),
+            (2016, CenturyInference::Ambiguous { matches: 0 }));
+
+        // Day 60 of 2000 is Tuesday, 02-29, in 1900 it was Thursday, 03-01
+        assert_eq!(policy.full_year_of_day(0, 60, Some(Weekday::Tuesday)),
+            (2000, CenturyInference::Unique));
+        assert_eq!(policy.full_year_of_day(0, 60, Some(Weekday::Thursday)),
+            (1900, CenturyInference::Unique));
+
+        // Without a day of week, within 50 years of the reference date
+        assert_eq!(policy.full_year_without_weekday(75), (2075, CenturyInference::NoWeekday));
+        assert_eq!(policy.full_year_without_weekday(76), (1976, CenturyInference::NoWeekday));
+        assert_eq!(CenturyPolicy::default().full_year_without_weekday(77), (2077, CenturyInference::Configured));
+    }
+}
@@ -0,0 +1,112 @@
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+
+use crate::time_code::{LeapSecond, RefclockSample};
+
+/* chrony's `refclock SOCK`: chronyd listens on a Unix datagram socket for
+ * samples, each the system time of a measurement and the offset of the true
+ * time from it. Our samples carry the full time, so they are not marked as
+ * pulses (chrony would only use the fraction of a second then).
+ *
+ * Sent once per confirmed minute mark, or every second once locked.
+ */
+
+const SOCK_MAGIC: libc::c_int = 0x534f_434b;
+
+// Layout of `struct sock_sample` in chrony's refclock_sock.c
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct SockSample {
+    tv: libc::timeval,
+    offset: f64,
+    pulse: libc::c_int,
+    leap: libc::c_int,
+    _pad: libc::c_int,
+    magic: libc::c_int
+}
+
+impl SockSample {
+    fn new(sample: &RefclockSample) -> SockSample {
+        let leap = match sample.leap_second {
+            LeapSecond::None => 0,
+            LeapSecond::Inserted => 1,
+            LeapSecond::Deleted => 2,
+        };
+        SockSample {
+            tv: libc::timeval {
+                tv_sec: sample.receive.as_second() as libc::time_t,
+                tv_usec: (sample.receive.subsec_nanosecond() / 1000) as libc::suseconds_t
+            },
+            offset: sample.offset().as_secs_f64(),
+            pulse: 0,
+            leap,
+            _pad: 0,
+            magic: SOCK_MAGIC
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        // SAFETY: plain C struct, all fields initialized
+        unsafe { std::slice::from_raw_parts((self as *const SockSample).cast(), size_of::<SockSample>()) }
+    }
+}
+
+pub struct ChronySock {
+    socket: UnixDatagram,
+    path: PathBuf,
+    every_second: bool
+}
+
+impl ChronySock {
+    pub fn new(path: impl AsRef<Path>, every_second: bool) -> io::Result<ChronySock> {
+        Ok(ChronySock {
+            socket: UnixDatagram::unbound()?,
+            path: path.as_ref().to_path_buf(),
+            every_second
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Whether to send every second, not just minute marks
+    pub fn every_second(&self) -> bool {
+        self.every_second
+    }
+
+    // Fails while chronyd is not listening
+    pub fn send(&self, sample: &RefclockSample) -> io::Result<()> {
+        self.socket.send_to(SockSample::new(sample).as_bytes(), &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jiff::Timestamp;
+    use super::*;
+
+    #[test]
+    fn test_send() {
+        let path = std::env::temp_dir().join(format!("rust-tools-chrony-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixDatagram::bind(&path).expect("Could not bind socket");
+        let chrony = ChronySock::new(&path, false).expect("Could not create socket");
+
+        let clock: Timestamp = "2016-01-01T00:00:00Z".parse().unwrap();
+        let receive: Timestamp = "2015-12-31T23:59:59.75Z".parse().unwrap();
+        chrony.send(&RefclockSample { clock, receive, leap_second: LeapSecond::Inserted }).expect("Could not send");
+
+        let mut buf = [0u8; 64];
+        let len = listener.recv(&mut buf).expect("Could not receive");
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(len, size_of::<SockSample>());
+        // SAFETY: exactly one sample was received
+        let sample: SockSample = unsafe { std::ptr::read_unaligned(buf.as_ptr().cast()) };
+        assert_eq!((sample.tv.tv_sec as i64, sample.tv.tv_usec as i64), (receive.as_second(), 750_000));
+        assert_eq!(sample.offset, 0.25);
+        assert_eq!((sample.pulse, sample.leap, sample.magic), (0, 1, SOCK_MAGIC));
+    }
+}
@@ -1,11 +1,19 @@
-use std::fmt::{self};
+use core::fmt::{self};
+use core::ops::{Range, RangeInclusive};
 
-use jiff::{Zoned, civil::DateTime, tz};
-use num_traits::NumCast;
-use ringbuffer::{RingBuffer,AllocRingBuffer};
+use jiff::{Zoned, civil::{Date, Weekday}, tz};
+use ringbuffer::{RingBuffer, ConstGenericRingBuffer};
 // use serialport::Error;
 
+use crate::bit_frame::{BitEnum, BitFrame, BitRange, FrameError};
 use crate::bitrep::*;
+use crate::century::{CenturyInference, CenturyPolicy};
+#[cfg(feature = "std")]
+use crate::frame_view::{self, FieldSpan, Highlight, RenderStyle};
+#[cfg(feature = "std")]
+use crate::pulse_decoder::{self, BAUD_50, PulseTiming};
+#[cfg(feature = "std")]
+use crate::time_code::{self, DecodedFrame, LeapSecond, TimeCodeDecoder, symbol_char};
 
 /* Encoding is:
  * Second       Contents
@@ -37,7 +45,45 @@ use crate::bitrep::*;
  * 58           P3    - Date Parity (even)
  * 59                 - usually missing (minute indication), except for leap insertion
  */
-pub const DECODE_HEADER : &str = "---------------RADMLS1248124P124812P1248121241248112481248P_";
+/* The table above as a `BitFrame`, which gives the field decoders, the
+    header line, the layout for rendering and `ParityBitName`. Clippy takes
+    the repeated layout attributes for duplicates.
+ */
+#[allow(clippy::duplicated_attributes)]
+#[derive(Clone, Debug, PartialEq, BitFrame)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[frame(len = 60, parity = ParityBitName)]
+#[fixed(name = "start_of_minute", bits = 0, label = "M")]
+#[fixed(name = "minute_marker", bits = 59, label = "_", skipped)]
+#[parity(name = "minute", bits = 21..29, label = "P1")]
+#[parity(name = "hour", bits = 29..36, label = "P2")]
+#[parity(name = "date", bits = 36..59, label = "P3")]
+pub struct Dcf77Fields {
+    #[field(bits = 1..15, raw, label = "meteotime")]
+    pub meteotime: [Bit; 14],
+    #[field(bits = 15, label = "R")]
+    pub call_bit: bool,
+    #[field(bits = 16, label = "A1")]
+    pub zone_change: bool,
+    #[field(bits = 17..19, enumeration, labels = ["Z1", "Z2"], header = "DM")]
+    pub timezone: Dcf77Tz,
+    #[field(bits = 19, label = "A2", header = "L")]
+    pub leap_second: bool,
+    #[field(bits = 20, label = "S")]
+    pub start_of_time_code: bool,
+    #[field(bits = 21..28, bcd = [4, 3], labels = ["min", "10m"])]
+    pub minute: i8,
+    #[field(bits = 29..35, bcd = [4, 2], labels = ["hour", "10h"])]
+    pub hour: i8,
+    #[field(bits = 36..42, bcd = [4, 2], labels = ["day", "10d"])]
+    pub day: i8,
+    #[field(bits = 42..45, bcd = [3], labels = ["dow"])]
+    pub weekday: i8,
+    #[field(bits = 45..50, bcd = [4, 1], labels = ["mon", "10m"])]
+    pub month: i8,
+    #[field(bits = 50..58, bcd = [4, 4], labels = ["year", "10y"])]
+    pub year: i8
+}
 
 /* Macro for error testing, borrowed from the "matches" crate:
     https://docs.rs/matches/0.1.10/matches/macro.assert_matches.html
@@ -54,21 +100,139 @@ macro_rules! assert_matches {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum ParityBitName {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Field {
+    Timezone,
+    StartOfTimeCode,
     Minute,
     Hour,
-    Date
+    Day,
+    Weekday,
+    Month,
+    Year,
+    MinuteMarker
+}
+
+impl Field {
+    const ALL: [Field; 9] = [
+        Self::Timezone, Self::StartOfTimeCode, Self::Minute, Self::Hour, Self::Day,
+        Self::Weekday, Self::Month, Self::Year, Self::MinuteMarker
+    ];
+
+    // The field bit `n` belongs to, if any (flags, meteotime and parity bits are not fields)
+    pub fn containing(n: usize) -> Option<Field> {
+        Self::ALL.into_iter().find(|field| field.bit_range().contains(&n))
+    }
+
+    pub fn bit_range(&self) -> Range<usize> {
+        match *self {
+            Self::Timezone          => Dcf77Fields::TIMEZONE_BITS,
+            Self::StartOfTimeCode   => Dcf77Fields::START_OF_TIME_CODE_BITS,
+            Self::Minute            => Dcf77Fields::MINUTE_BITS,
+            Self::Hour              => Dcf77Fields::HOUR_BITS,
+            Self::Day               => Dcf77Fields::DAY_BITS,
+            Self::Weekday           => Dcf77Fields::WEEKDAY_BITS,
+            Self::Month             => Dcf77Fields::MONTH_BITS,
+            Self::Year              => Dcf77Fields::YEAR_BITS,
+            Self::MinuteMarker      => Dcf77Fields::MINUTE_MARKER_BITS,
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match *self {
+            Self::Timezone          => "time zone",
+            Self::StartOfTimeCode   => "start of time code",
+            Self::Minute            => "minute",
+            Self::Hour              => "hour",
+            Self::Day               => "day",
+            Self::Weekday           => "day of week",
+            Self::Month             => "month",
+            Self::Year              => "year",
+            Self::MinuteMarker      => "minute marker",
+        })
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DecodingFailure {
     NotEnoughBits,
-    MissingBit,
+    MissingBit(usize),
     ParityError(ParityBitName),
     MissingStartOfTimeCode,
     NotSync,    /* Missing "skipped" bit 59 */
     InvalidTimezoneBits,
-    BCDNotBigenough
+    InvalidDigit(Field, u8),
+    OutOfRange(Field, i16)
+}
+
+impl DecodingFailure {
+    pub const KINDS: [&'static str; 8] = ["NotEnoughBits", "MissingBit", "ParityError", "MissingStartOfTimeCode", "NotSync", "InvalidTimezoneBits", "InvalidDigit", "OutOfRange"];
+
+    // The variant without its details, e.g. for metrics labels
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            Self::NotEnoughBits          => "NotEnoughBits",
+            Self::MissingBit(_)          => "MissingBit",
+            Self::ParityError(_)         => "ParityError",
+            Self::MissingStartOfTimeCode => "MissingStartOfTimeCode",
+            Self::NotSync                => "NotSync",
+            Self::InvalidTimezoneBits    => "InvalidTimezoneBits",
+            Self::InvalidDigit(..)       => "InvalidDigit",
+            Self::OutOfRange(..)         => "OutOfRange",
+        }
+    }
+
+    // The field the failure is about, if it concerns a single one
+    pub fn field(&self) -> Option<Field> {
+        match *self {
+            Self::MissingStartOfTimeCode    => Some(Field::StartOfTimeCode),
+            Self::NotSync                   => Some(Field::MinuteMarker),
+            Self::InvalidTimezoneBits       => Some(Field::Timezone),
+            Self::InvalidDigit(field, _)    => Some(field),
+            Self::OutOfRange(field, _)      => Some(field),
+            Self::MissingBit(n)             => Field::containing(n),
+            _ => None
+        }
+    }
+
+    // The bits in the 60-bit frame involved in the failure
+    pub fn bit_range(&self) -> Option<Range<usize>> {
+        match *self {
+            Self::MissingBit(n)     => Some(n..n + 1),
+            Self::ParityError(name) => Some(name.bit_range()),
+            _ => self.field().map(|field| field.bit_range())
+        }
+    }
+}
+
+impl fmt::Display for DecodingFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::NotEnoughBits         => write!(f, "not enough bits for a full minute"),
+            Self::MissingBit(n)         => match Field::containing(n) {
+                Some(field) => write!(f, "{} bit {} is missing", field, n),
+                None => {
+                    let label = Dcf77Fields::LAYOUT.iter()
+                        .find(|span| span.bits.contains(&n))
+                        .map_or("?", |span| span.label);
+                    write!(f, "bit {} ({}) is missing", n, label)
+                }
+            },
+            Self::ParityError(name)     =>
+                write!(f, "{} parity error ({})", name, BitRange(name.bit_range())),
+            Self::InvalidDigit(field, v) =>
+                write!(f, "invalid {} digit {} ({})", field, v, BitRange(field.bit_range())),
+            Self::OutOfRange(field, v)  =>
+                write!(f, "{} {} out of range ({})", field, v, BitRange(field.bit_range())),
+            Self::MissingStartOfTimeCode | Self::NotSync | Self::InvalidTimezoneBits => {
+                let field = self.field().expect("Failure should have a field");
+                write!(f, "invalid {} ({})", field, BitRange(field.bit_range()))
+            }
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -79,33 +243,84 @@ pub enum Error {
 
 #[allow(unused)]
 impl Error {
-    fn decoding_failure(&self) -> Option<DecodingFailure> {
+    // `DecodingFailure::kind`, or "JiffError"
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::DecodingError(e) => e.kind(),
+            Self::JiffError(_) => "JiffError",
+        }
+    }
+
+    pub fn decoding_failure(&self) -> Option<DecodingFailure> {
         match self {
             Self::DecodingError(e) => Some(*e),
             _ => None
         }
     }
 
-    fn jiff_error(&self) -> Option<jiff::Error> {
+    pub fn jiff_error(&self) -> Option<jiff::Error> {
         match self {
             Self::JiffError(e) => Some(e.clone()),
             _ => None
         }
     }
+
+    pub fn bit_range(&self) -> Option<Range<usize>> {
+        self.decoding_failure().and_then(|e| e.bit_range())
+    }
 }
 
-/* TODO: Traits for error (see e.g. jiff via in_tz())
-    impl std::error::Error for Error {}
-    impl core::fmt::Display for Error
-    impl core::fmt::Debug for Error
- */
+impl core::error::Error for Error {}
+
+#[cfg(feature = "std")]
+impl time_code::StationError for Error {
+    fn kind(&self) -> &'static str {
+        Error::kind(self)
+    }
+
+    fn is_not_sync(&self) -> bool {
+        matches!(self, Self::DecodingError(DecodingFailure::NotSync))
+    }
+}
 
-impl std::error::Error for Error {}
+// Failures as their variant, errors from jiff only by their message
+#[cfg(feature = "serde")]
+impl serde::Serialize for Error {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+        match self {
+            Self::DecodingError(e) => serializer.serialize_newtype_variant("Error", 0, "DecodingError", e),
+            Self::JiffError(e) => serializer.serialize_newtype_variant("Error", 1, "JiffError", &format_args!("{}", e)),
+        }
+    }
+}
 
 impl core::fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // FIXME
-        write!(f, "{:?}", self)
+        match self {
+            Self::DecodingError(e) => write!(f, "{}", e),
+            Self::JiffError(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/* An error together with the raw bits involved, for log analysis */
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FailureReport {
+    pub error: Error,
+    pub bit_range: Option<Range<usize>>,
+    pub bits: Vec<Bit>
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for FailureReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)?;
+        if !self.bits.is_empty() {
+            write!(f, ": {}", BitString::from(self.bits.as_slice()))?;
+        }
+        Ok(())
     }
 }
 
@@ -121,7 +336,7 @@ impl From<jiff::Error> for Error {
     }
 }
 
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
 
 impl<T> From<DecodingFailure> for Result<T> {
     fn from(value: DecodingFailure) -> Self {
@@ -129,96 +344,166 @@ impl<T> From<DecodingFailure> for Result<T> {
     }
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Dcf77Tz {
     MEZ,
     MESZ
 }
 
 impl Dcf77Tz {
-    fn to_utc_offset(&self) -> tz::Offset {
-        match *self {
+    fn to_utc_offset(self) -> tz::Offset {
+        match self {
             Self::MEZ => tz::offset(1),
             Self::MESZ => tz::offset(2),
         }
     }
-    fn to_time_zone(&self) -> tz::TimeZone {
+    fn to_time_zone(self) -> tz::TimeZone {
         self.to_utc_offset().to_time_zone()
     }
 }
 
-impl std::fmt::Display for Dcf77Tz {
+impl fmt::Display for Dcf77Tz {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:?}", self)
     }
 }
 
-impl FromBits for Result<Dcf77Tz> {
-    fn from_bits_iter<B: PureBit, T: Iterator<Item = B>>(iter: T) -> Self {
-        /* We read everything as LSB, so this method will also be called with
-            an already reversed iterator. Thus using from_bits_iter for another
-            type (here: u8) will return an int with LSB order.
-        */
-        let v: u8 = FromBits::from_bits_iter(iter);
-        match v {
-            0b10 /* Bits: zero, one */ => Ok(Dcf77Tz::MEZ),
-            0b01 /* Bits: one, zero */ => Ok(Dcf77Tz::MESZ),
-            _    => From::from(DecodingFailure::InvalidTimezoneBits)
+impl BitEnum for Dcf77Tz {
+    // Z1 is the lsb
+    fn from_bit_value(value: u32) -> Option<Self> {
+        match value {
+            0b10 /* Bits: zero, one */ => Some(Dcf77Tz::MEZ),
+            0b01 /* Bits: one, zero */ => Some(Dcf77Tz::MESZ),
+            _    => None
+        }
+    }
+
+    fn to_bit_value(&self) -> u32 {
+        match *self {
+            Self::MEZ => 0b10,
+            Self::MESZ => 0b01,
         }
     }
 }
 
-fn check_parity(name: ParityBitName, bits: &[Bit]) -> Result<()> {
-    let mut parity = false; /* even */
-    for bit in bits {
-        let value = bit.to_bit()
-            .ok_or(DecodingFailure::MissingBit)?; // Why does this work, but not (1)
-        parity ^= value;
+// Failures of the generated field decoders, see `Dcf77Fields`
+fn field_failure(field: Field, error: FrameError<ParityBitName>) -> DecodingFailure {
+    match error {
+        FrameError::NotEnoughBits => DecodingFailure::NotEnoughBits,
+        FrameError::NotSync => DecodingFailure::NotSync,
+        FrameError::MissingBit(n) => DecodingFailure::MissingBit(n),
+        FrameError::ParityError(name) => DecodingFailure::ParityError(name),
+        FrameError::InvalidDigit(_, value) => DecodingFailure::InvalidDigit(field, value),
+        FrameError::InvalidValue(..) if field == Field::Timezone => DecodingFailure::InvalidTimezoneBits,
+        FrameError::InvalidValue(_, value) => DecodingFailure::OutOfRange(field, value as i16),
     }
-    // FIXME: simplify! how?
-    if parity { From::from(DecodingFailure::ParityError(name)) }
-    else { Ok(()) }
 }
 
-fn decode_bits<T>(bits: &[Bit]) -> Result<T> where
-    T: FromBits
-{
-    FromBits::from_maybebits_lsb(bits)
-        .ok_or(From::from(DecodingFailure::MissingBit)) // (1)
+fn check_parity(name: ParityBitName, bits: &[Bit]) -> Result<()> {
+    let checked = match name {
+        ParityBitName::Minute   => Dcf77Fields::check_minute_parity(bits),
+        ParityBitName::Hour     => Dcf77Fields::check_hour_parity(bits),
+        ParityBitName::Date     => Dcf77Fields::check_date_parity(bits),
+    };
+    checked.map_err(|e| match e {
+        FrameError::MissingBit(n) => From::from(DecodingFailure::MissingBit(n)),
+        _ => From::from(DecodingFailure::ParityError(name))
+    })
+}
+
+/* A successfully decoded frame: the start of the minute it announces,
+    plus how we chose the century for it.
+ */
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dcf77Frame {
+    pub time: Zoned,
+    // A1: summer time starts or ends at the end of the hour
+    pub zone_change: bool,
+    // A2: a leap second is inserted at the end of the hour
+    pub leap_second: bool,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_util::option_weekday"))]
+    pub weekday: Option<Weekday>,
+    pub century: CenturyInference
 }
 
-fn decode_bcd<T>(lower_bits: &[Bit], higher_bits: &[Bit]) -> Result<T> where
-    T: FromBits + num_traits::PrimInt
-{
-    let ten: T = NumCast::from(10)
-        .ok_or(DecodingFailure::BCDNotBigenough)?;
-    let lower: T = decode_bits(lower_bits)?;
-    let higher: T = decode_bits(higher_bits)?;
-    Ok(higher * ten + lower)
+/* Result of partial decoding: every field group that could be decoded,
+    plus the reasons why the others could not.
+ */
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PartialFrame {
+    pub timezone: Option<Dcf77Tz>,
+    pub minute: Option<i8>,
+    pub hour: Option<i8>,
+    pub date: Option<Date>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_util::option_weekday"))]
+    pub weekday: Option<Weekday>,
+    pub century: Option<CenturyInference>,
+    pub failures: Vec<Error>
+}
+
+#[cfg(feature = "std")]
+impl PartialFrame {
+    pub fn is_complete(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for PartialFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.date {
+            Some(date) => write!(f, "{} ", date)?,
+            None => write!(f, "????-??-?? ")?,
+        }
+        match self.hour {
+            Some(hour) => write!(f, "{:02}:", hour)?,
+            None => write!(f, "??:")?,
+        }
+        match self.minute {
+            Some(minute) => write!(f, "{:02} ", minute)?,
+            None => write!(f, "?? ")?,
+        }
+        match self.timezone {
+            Some(tz) => write!(f, "{}", tz),
+            None => write!(f, "???"),
+        }
+    }
 }
 
 pub struct Decoder {
-    buffer: AllocRingBuffer<Bit>
-    // let mut buffer = AllocRingBuffer::with_capacity(2);
+    buffer: ConstGenericRingBuffer<Bit, 60>,
+    century: CenturyPolicy
 }
 
+#[cfg(feature = "std")]
 impl fmt::Display for Decoder {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for x in self.buffer.iter() {
-            write!(f, "{}", x)?;
-        }
-        Ok(())
+        BitString::from_iter(self.buffer.iter().copied()).fmt(f)
     }
 }
 
-#[allow(unused)]
 impl Decoder {
     pub fn new() -> Decoder {
         Decoder {
-            buffer: AllocRingBuffer::new(60)
+            buffer: ConstGenericRingBuffer::new(),
+            century: CenturyPolicy::default()
         }
     }
 
+    pub fn with_century(mut self, century: CenturyPolicy) -> Decoder {
+        self.century = century;
+        self
+    }
+
+    pub fn century(&self) -> CenturyPolicy {
+        self.century
+    }
+
     pub fn len(&self) -> usize {
         self.buffer.len()
     }
@@ -228,13 +513,18 @@ impl Decoder {
     }
 
     // FIXME: Remove?
+    #[cfg(feature = "std")]
     pub fn to_vec(&self) -> Vec<Bit> {
         self.buffer.to_vec()
     }
 
+    // Only meaningful once the buffer is full
+    pub fn frame(&self) -> [Bit; 60] {
+        core::array::from_fn(|n| self.buffer.get(n).copied().unwrap_or(Bit::Unknown))
+    }
+
     pub fn add_maybe_bit<V: MaybeBit>(&mut self, value: V) -> &Self {
-        self.buffer.enqueue(to_bit(value));
-        self
+        self.add_bit(to_bit(value))
     }
 
     pub fn add_bit(&mut self, value: Bit) -> &Self {
@@ -242,31 +532,33 @@ impl Decoder {
         self
     }
 
+    #[cfg(feature = "std")]
     pub fn add_bits<B, V>(&mut self, count: usize, value: V) -> &Self where
         B: MaybeBit + Copy,
         V: MaybeBits<BitElem = B>
     {
         assert!(count <= value.bit_len());
         let bitvec = value.to_bit_vector();
-        let bits = (&bitvec[0..count]).iter().map(|b| to_bit(*b));
+        let bits = bitvec[0..count].iter().map(|b| to_bit(*b));
         self.add_bit_iter(bits);
         self
     }
 
-    pub fn add_bitvec(&mut self, count: usize, bitvec: &Vec<Bit>) -> &Self {
+    pub fn add_bitvec(&mut self, count: usize, bitvec: &[Bit]) -> &Self {
         assert!(count <= bitvec.len());
-        self.add_bit_iter(bitvec[0..count].iter().map(|b| *b));
+        self.add_bit_iter(bitvec[0..count].iter().copied());
         self
     }
 
     pub fn add_bit_iter(&mut self, iter: impl IntoIterator<Item = Bit>) -> &Self {
-        self.buffer.extend(iter);
+        for bit in iter {
+            self.add_bit(bit);
+        }
         self
     }
 
     pub fn add_bit_ref_iter<'a>(&mut self, iter: impl IntoIterator<Item = &'a Bit>) -> &Self {
-        self.buffer.extend(iter.into_iter().copied());
-        self
+        self.add_bit_iter(iter.into_iter().copied())
     }
 
     // FIXME: Implement indexing trait
@@ -275,141 +567,290 @@ impl Decoder {
         self.buffer[index]
     }
 
-    // fn decode_bits(&self, r: Range<usize>) {
-    //     let vs = &(self.buffer.to_vec()[r]);
+    /* Annotated view of the current frame: failed parity groups and
+        unknown bits are highlighted. Before the buffer is full, the bits
+        can not be assigned to fields yet and are printed as they are.
+     */
+    #[cfg(feature = "std")]
+    pub fn render(&self, style: RenderStyle) -> String {
+        if !self.buffer.is_full() {
+            return format!("{:>60}\n{:>60}\n", Dcf77Fields::HEADER, self);
+        }
+        let bitvec = self.frame();
+        let mut highlights: Vec<Option<Highlight>> = vec![None; bitvec.len()];
+        for name in ParityBitName::ALL {
+            if let Err(Error::DecodingError(DecodingFailure::ParityError(_))) = check_parity(name, &bitvec) {
+                highlights[name.bit_range()].fill(Some(Highlight::ParityFailed));
+            }
+        }
+        for (n, bit) in bitvec[..59].iter().enumerate() {
+            if bit.to_bit().is_none() {
+                highlights[n] = Some(Highlight::Unknown);
+            }
+        }
+        frame_view::render(&bitvec, Dcf77Fields::LAYOUT, &highlights, style)
+    }
 
-    // }
+    // Attach the raw bits involved in an error
+    #[cfg(feature = "std")]
+    pub fn report(&self, error: &Error) -> FailureReport {
+        let bit_range = error.bit_range();
+        let bits = match bit_range {
+            Some(ref range) if range.end <= self.buffer.len() =>
+                range.clone().map(|n| self.buffer[n]).collect(),
+            _ => Vec::new()
+        };
+        FailureReport { error: error.clone(), bit_range, bits }
+    }
 
     pub fn decode_dcf77(&self) -> Result<Zoned> {
+        self.decode_frame().map(|frame| frame.time)
+    }
+
+    pub fn decode_frame(&self) -> Result<Dcf77Frame> {
         if !self.buffer.is_full() {
             return From::from(DecodingFailure::NotEnoughBits)
         }
-        let bitvec = self.buffer.to_vec();
+        let bitvec = self.frame();
 
         // "Sync": Bit 59 should be skipped
         if bitvec[59] != Bit::Skipped {
             return From::from(DecodingFailure::NotSync)
         }
 
-        let dcf77_tz_res: Result<Dcf77Tz> = decode_bits(&bitvec[17..19])?;
-        let dcf77_tz: Dcf77Tz = dcf77_tz_res?;
-
-        if bitvec[20] != Bit::Value(true) {
-            return From::from(DecodingFailure::MissingStartOfTimeCode)
-        }
-        check_parity(ParityBitName::Minute, &bitvec[21..29])?;
-        let minute: i8 = decode_bcd(&bitvec[21..25], &bitvec[25..28])?;
-        check_parity(ParityBitName::Hour, &bitvec[29..36])?;
-        let hour: i8 = decode_bcd(&bitvec[29..33], &bitvec[33..35])?;
-        check_parity(ParityBitName::Date, &bitvec[36..59])?;
-        let day: i8 = decode_bcd(&bitvec[36..40], &bitvec[40..42])?;
-        let month: i8 = decode_bcd(&bitvec[45..49], &bitvec[49..50])?;
-        let year: i8 = decode_bcd(&bitvec[50..54], &bitvec[54..58])?;
-
-        let full_year: i16 = 2000 + year as i16;
-
-        /* jiff date() / at() / in_tz() args:
-            year: i16,
-            month: i8,
-            day: i8
-            hour: i8,
-            minute: i8,
-            second: i8,
-            subsec_nanosecond: i32,
-            time_zone_name: &str
-         */
-        let dt = DateTime::new(
-            full_year, month, day,
-            hour, minute, 0, 0
-        );
-        let tz = dcf77_tz.to_time_zone();
-        match dt {
-            Ok(value) => {
-                let x = value.to_zoned(tz)?;
-                Ok(x)
-            }
-            // Err(e) if e.is_range() => {
-            //     Err(From::from(DecodingFailure::ParityError))
-            // }
-            Err(e) => Err(From::from(e))
+        let tz = decode_timezone(&bitvec)?;
+        check_start_of_time_code(&bitvec)?;
+        let minute = decode_minute(&bitvec)?;
+        let hour = decode_hour(&bitvec)?;
+        let date = decode_date(&bitvec, self.century)?;
+
+        let time = date.date
+            .at(hour, minute, 0, 0)
+            .to_zoned(tz.to_time_zone())?;
+        let zone_change = Dcf77Fields::decode_zone_change(&bitvec) == Ok(true);
+        let leap_second = Dcf77Fields::decode_leap_second(&bitvec) == Ok(true);
+        Ok(Dcf77Frame { time, zone_change, leap_second, weekday: date.weekday, century: date.century })
+    }
+
+    /* Decode every field whose parity group is intact, even if others fail.
+        Under marginal reception the long date block fails far more often
+        than minutes and hours.
+     */
+    #[cfg(feature = "std")]
+    pub fn decode_partial(&self) -> PartialFrame {
+        let mut partial = PartialFrame::default();
+        if !self.buffer.is_full() {
+            partial.failures.push(From::from(DecodingFailure::NotEnoughBits));
+            return partial
+        }
+        let bitvec = self.frame();
+        if bitvec[59] != Bit::Skipped {
+            partial.failures.push(From::from(DecodingFailure::NotSync));
+            return partial
         }
+
+        let failures = &mut partial.failures;
+        partial.timezone = keep_ok(decode_timezone(&bitvec), failures);
+        keep_ok(check_start_of_time_code(&bitvec), failures);
+        partial.minute = keep_ok(decode_minute(&bitvec), failures);
+        partial.hour = keep_ok(decode_hour(&bitvec), failures);
+        if let Some(date) = keep_ok(decode_date(&bitvec, self.century), failures) {
+            partial.date = Some(date.date);
+            partial.weekday = date.weekday;
+            partial.century = Some(date.century);
+        }
+        partial
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use jiff::{Unit, Zoned};
-    use super::*;
+#[cfg(feature = "std")]
+impl TimeCodeDecoder for Decoder {
+    fn name(&self) -> &'static str {
+        "dcf77"
+    }
 
-    fn add_bits_helper<B, V>(rb: &mut AllocRingBuffer<Bit>, count: usize, value: V) where
-        B: MaybeBit + Copy,
-        V: MaybeBits<BitElem = B>
-    {
-        assert!(count <= value.bit_len());
-        let bitvec = value.to_bit_vector();
-        let bits = (&bitvec[0..count]).iter().map(|b| to_bit(*b));
-        rb.extend(bits);
+    fn pulse_timing(&self) -> PulseTiming {
+        BAUD_50
+    }
+
+    fn failure_kinds(&self) -> Vec<&'static str> {
+        time_code::failure_kinds(&DecodingFailure::KINDS)
     }
 
-    fn build_valid_signal() -> Vec<Bit> {
-        let mut buffer: AllocRingBuffer<Bit> = AllocRingBuffer::new(60);
+    fn pulse_thresholds(&self) -> Vec<(u32, char)> {
+        time_code::width_thresholds(&pulse_decoder::DCF77_CLASSES)
+    }
 
-        // 0-10 Varied
-        for _ in 0..11 {
-            buffer.enqueue(Bit::Unknown);
-        }
+    fn frame_len(&self) -> usize {
+        60
+    }
+
+    fn alphabet(&self) -> &'static str {
+        "01_?"
+    }
+
+    fn layout(&self) -> &'static [FieldSpan] {
+        Dcf77Fields::LAYOUT
+    }
 
-        // 11-14 Free
-        for _ in 11..15 {
-            buffer.enqueue(Bit::Unknown);
+    fn feed_pulse(&mut self, pulse: u8) -> char {
+        let bit = pulse_decoder::decode_pulse(pulse);
+        self.add_bit(bit);
+        symbol_char(bit)
+    }
+
+    fn feed_symbol(&mut self, symbol: char) -> bool {
+        let Some(bit) = Bit::from_char(symbol) else { return false };
+        self.add_bit(bit);
+        true
+    }
+
+    fn gaps(&self) -> &'static [usize] {
+        &[59]
+    }
+
+    // The minute marker is the (missing) last second
+    fn is_synced(&self) -> bool {
+        self.is_full() && self.get_bit(59) == Bit::Skipped
+    }
+
+    fn frame_bits(&self) -> Vec<Bit> {
+        self.to_vec()
+    }
+
+    fn decode(&self) -> time_code::Result<DecodedFrame> {
+        let frame = self.decode_frame()?;
+        let leap_second = if frame.leap_second { LeapSecond::Inserted } else { LeapSecond::None };
+        let summer_time = frame.time.offset() == Dcf77Tz::MESZ.to_utc_offset();
+        Ok(DecodedFrame {
+            next_frame: frame.time.clone(),
+            time: frame.time,
+            century: frame.century,
+            leap_second,
+            summer_time,
+            summer_time_change: frame.zone_change
+        })
+    }
+
+    fn report(&self, error: &time_code::Error) -> String {
+        match error.downcast_ref::<Error>() {
+            Some(e) => Decoder::report(self, e).to_string(),
+            None => error.to_string()
         }
+    }
 
-        // 15,16 n.n.
-        buffer.enqueue(to_bit(false));
-        buffer.enqueue(to_bit(false));
-        // 17,18 time zone
-        buffer.enqueue(to_bit(false));
-        buffer.enqueue(to_bit(true));
-        // 19 n.n.
-        buffer.enqueue(to_bit(false));
-        // 20 fix 1
-        buffer.enqueue(to_bit(true));
+    fn partial(&self) -> Option<String> {
+        Some(self.decode_partial().to_string())
+    }
 
-        // 21-28 Minutes + Parity
-        add_bits_helper(&mut buffer, 4, 0);
-        add_bits_helper(&mut buffer, 3, 0);
-        buffer.enqueue(to_bit(false));
+    fn render(&self, style: RenderStyle) -> String {
+        Decoder::render(self, style)
+    }
+}
 
-        // 29-35 Hours + Parity
-        add_bits_helper(&mut buffer, 4, 0);
-        add_bits_helper(&mut buffer, 2, 0);
-        buffer.enqueue(to_bit(false));
+/***************************************************************************************************
+ * Field groups, each protected by their own parity (except for the time zone)
+ */
 
-        // 36-41 Days
-        add_bits_helper(&mut buffer, 4, 1);
-        add_bits_helper(&mut buffer, 2, 0);
+struct DecodedDate {
+    date: Date,
+    weekday: Option<Weekday>,
+    century: CenturyInference
+}
 
-        // 42-44 Day of week
-        add_bits_helper(&mut buffer, 3, 1 /* Monday */);
+fn decode_timezone(bits: &[Bit]) -> Result<Dcf77Tz> {
+    Dcf77Fields::decode_timezone(bits)
+        .map_err(|e| From::from(field_failure(Field::Timezone, e)))
+}
 
-        // 45-49 Month
-        add_bits_helper(&mut buffer, 4, 1);
-        add_bits_helper(&mut buffer, 1, 0);
+fn check_start_of_time_code(bits: &[Bit]) -> Result<()> {
+    if Dcf77Fields::decode_start_of_time_code(bits) != Ok(true) {
+        return From::from(DecodingFailure::MissingStartOfTimeCode)
+    }
+    Ok(())
+}
 
-        // 50-57 Years
-        add_bits_helper(&mut buffer, 4, 6);
-        add_bits_helper(&mut buffer, 4, 1);
+// The field decoders check the parity of their group first
+fn decode_field(field: Field, decoded: core::result::Result<i8, FrameError<ParityBitName>>, valid: RangeInclusive<i8>) -> Result<i8> {
+    let value = decoded.map_err(|e| field_failure(field, e))?;
+    if !valid.contains(&value) {
+        return From::from(DecodingFailure::OutOfRange(field, value as i16))
+    }
+    Ok(value)
+}
 
-        // 58 Date parity
-        buffer.enqueue(to_bit(false));
+fn decode_minute(bits: &[Bit]) -> Result<i8> {
+    decode_field(Field::Minute, Dcf77Fields::decode_minute(bits), 0..=59)
+}
 
-        // 59 Missing
-        buffer.enqueue(Bit::Skipped);
-        assert_eq!(buffer.len(), 60);
+fn decode_hour(bits: &[Bit]) -> Result<i8> {
+    decode_field(Field::Hour, Dcf77Fields::decode_hour(bits), 0..=23)
+}
+
+fn decode_date(bits: &[Bit], century_policy: CenturyPolicy) -> Result<DecodedDate> {
+    check_parity(ParityBitName::Date, bits)?;
+    let day = decode_field(Field::Day, Dcf77Fields::decode_day(bits), 1..=31)?;
+    let month = decode_field(Field::Month, Dcf77Fields::decode_month(bits), 1..=12)?;
+    let year = decode_field(Field::Year, Dcf77Fields::decode_year(bits), 0..=99)?;
+    let weekday_number = decode_field(Field::Weekday, Dcf77Fields::decode_weekday(bits), 0..=7)?;
+    let weekday = Weekday::from_monday_one_offset(weekday_number).ok();
+
+    let (full_year, century) = century_policy.full_year(year, month, day, weekday);
+    match Date::new(full_year, month, day) {
+        Ok(date) => Ok(DecodedDate { date, weekday, century }),
+        // All fields are in range by themselves, so the day does not fit the month
+        Err(_) => From::from(DecodingFailure::OutOfRange(Field::Day, day as i16))
+    }
+}
+
+#[cfg(feature = "std")]
+fn keep_ok<T>(result: Result<T>, failures: &mut Vec<Error>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            failures.push(e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        buffer.to_vec()
+    // 2016-01-01 00:00 MEZ, sent as a Monday (it was a Friday, see the century tests)
+    fn build_valid_signal() -> [Bit; 60] {
+        let fields = Dcf77Fields {
+            meteotime: [Bit::Unknown; 14],
+            call_bit: false,
+            zone_change: false,
+            timezone: Dcf77Tz::MEZ,
+            leap_second: false,
+            start_of_time_code: true,
+            minute: 0,
+            hour: 0,
+            day: 1,
+            weekday: 1,
+            month: 1,
+            year: 16
+        };
+        let mut bits = [Bit::Unknown; 60];
+        fields.encode_into(&mut bits).expect("Could not encode the frame");
+        bits
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_frame_layout() {
+        assert_eq!(Dcf77Fields::HEADER, "---------------RADMLS1248124P124812P1248121241248112481248P_");
+        let bits = build_valid_signal();
+        assert_eq!(BitString::from(bits.as_slice()).to_string(),
+            "???????????????00010100000000000000010000010010000011010000_");
+        let fields = Dcf77Fields::decode_bits(&bits).expect("Could not decode the frame");
+        assert_eq!((fields.timezone, fields.day, fields.year), (Dcf77Tz::MEZ, 1, 16));
+    }
+
+    #[cfg(feature = "std")]
     #[test]
     fn test_decoder_valid_signal() {
         let signal = build_valid_signal();
@@ -420,33 +861,30 @@ mod tests {
         assert_eq!(decoder.len(), 59);
 
         // Check that this does not decode
-        assert_eq!(decoder.is_full(), false);
+        assert!(!decoder.is_full());
         let decoded = decoder.decode_dcf77();
         assert_matches!(decoded, Err(Error::DecodingError(DecodingFailure::NotEnoughBits)));
         // assert_matches!(decoded, Err(Error::DecodingError(DecodingFailure::NotSync)));
 
         println!();
-        /* FIXME: right alignment doesn't work. This seems to be general rust problem:
-            https://users.rust-lang.org/t/should-display-implementations-respect-width-fill-align/110476
-         */
-        println!();
-        println!("{:>60}", DECODE_HEADER);
+        println!("{:>60}", Dcf77Fields::HEADER);
         println!("{:>60}", decoder);
+        assert_eq!(format!("{:>60}", decoder).find('?'), Some(1));
 
         // 59 Missing
         decoder.add_bit(Bit::Skipped);
         assert_eq!(decoder.len(), 60);
         // assert_eq!(decoder.decode_dcf77(), Ok(false));
 
-        assert_eq!(decoder.is_full(), true);
+        assert!(decoder.is_full());
         println!();
-        println!("{:>60}", DECODE_HEADER);
+        println!("{:>60}", Dcf77Fields::HEADER);
         println!("{:>60}", decoder);
         // for i in 0..60 {
         //     println!("    bit {:04}    {:?}", i, decoder.get_bit(i));
         // }
 
-        let now = Zoned::now().round(Unit::Second)
+        let now = jiff::Zoned::now().round(jiff::Unit::Second)
             .expect("Could not get current time");
         println!("now:    {}", now);
         let decoded = decoder.decode_dcf77()
@@ -482,8 +920,216 @@ mod tests {
         assert_eq!(decoder.len(), 60);
 
         let decoded = decoder.decode_dcf77();
-        println!("{:?}", decoded);
-        assert_matches!(decoded, Err(Error::JiffError(_)));
+        assert_matches!(decoded,
+            Err(Error::DecodingError(DecodingFailure::InvalidDigit(Field::Minute, 11))));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_decoder_error_report() {
+        let mut signal = build_valid_signal();
+        // 33rd of January (1100 11, lsb)
+        signal[36..42].copy_from_slice(&[
+            Bit::Value(true), Bit::Value(true), Bit::Value(false), Bit::Value(false),
+            Bit::Value(true), Bit::Value(true),
+        ]);
+        signal[58] = Bit::Value(true);
+        let mut decoder: Decoder = Decoder::new();
+        decoder.add_bit_ref_iter(signal.iter());
+
+        let error = decoder.decode_dcf77().expect_err("Day should be out of range");
+        assert_eq!(error.to_string(), "day 33 out of range (bits 36-41)");
+        let report = decoder.report(&error);
+        assert_eq!(report.bit_range, Some(36..42));
+        assert_eq!(report.to_string(), "day 33 out of range (bits 36-41): 110011");
+
+        // 31st of February
+        signal[36..42].copy_from_slice(&[
+            Bit::Value(true), Bit::Value(false), Bit::Value(false), Bit::Value(false),
+            Bit::Value(true), Bit::Value(true),
+        ]);
+        signal[45..49].copy_from_slice(&[
+            Bit::Value(false), Bit::Value(true), Bit::Value(false), Bit::Value(false),
+        ]);
+        signal[58] = Bit::Value(false);
+        decoder.add_bit_ref_iter(signal.iter());
+        let error = decoder.decode_dcf77().expect_err("Day should be out of range");
+        assert_eq!(error.to_string(), "day 31 out of range (bits 36-41)");
+
+        signal[30] = Bit::Unknown;
+        decoder.add_bit_ref_iter(signal.iter());
+        let error = decoder.decode_dcf77().expect_err("Hour bit is missing");
+        assert_eq!(error.to_string(), "hour bit 30 is missing");
+        assert_eq!(decoder.report(&error).to_string(), "hour bit 30 is missing: ?");
+
+        signal[30] = Bit::Value(false);
+        signal[35] = Bit::Unknown;
+        decoder.add_bit_ref_iter(signal.iter());
+        let error = decoder.decode_dcf77().expect_err("Hour parity bit is missing");
+        assert_eq!(error.to_string(), "bit 35 (P2) is missing");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_frame_lock() {
+        let signal = build_valid_signal();
+        let mut decoder = Decoder::new();
+        let mut lock = time_code::FrameLock::new(&decoder);
+        let mut feed = |decoder: &mut Decoder, bit: Bit| {
+            decoder.add_bit(bit);
+            let frame = decoder.is_synced().then(|| decoder.decode().ok()).flatten();
+            lock.update(bit.to_char(), frame.as_ref()).map(|time| time.to_string())
+        };
+
+        for bit in &signal[0..59] {
+            assert_eq!(feed(&mut decoder, *bit), None);
+        }
+        // The minute marker locks, and belongs to the previous minute
+        assert_eq!(feed(&mut decoder, Bit::Skipped).as_deref(), Some("2015-12-31T23:59:59+01:00[+01:00]"));
+        for bit in &signal[0..2] {
+            feed(&mut decoder, *bit);
+        }
+        assert_eq!(feed(&mut decoder, signal[2]).as_deref(), Some("2016-01-01T00:00:02+01:00[+01:00]"));
+
+        // A missing minute marker does not unlock, we keep counting seconds
+        for bit in &signal[3..59] {
+            feed(&mut decoder, *bit);
+        }
+        feed(&mut decoder, Bit::Value(true));
+        feed(&mut decoder, signal[0]);
+        assert_eq!(feed(&mut decoder, signal[1]).as_deref(), Some("2016-01-01T00:01:01+01:00[+01:00]"));
+
+        // A gap in the middle of the minute loses the lock
+        assert_eq!(feed(&mut decoder, Bit::Skipped), None);
+        assert!(!lock.is_locked());
+    }
+
+    #[test]
+    fn test_decoder_century_from_weekday() {
+        let mut signal = build_valid_signal();
+        let mut decoder = Decoder::new()
+            .with_century(CenturyPolicy::Weekday(jiff::civil::date(2026, 1, 10)));
+
+        // The test signal says Monday, 01.01.16: the closest match is 2216-01-01
+        decoder.add_bit_ref_iter(signal.iter());
+        let frame = decoder.decode_frame()
+            .expect("Could not decode dcf77 time");
+        assert_eq!(frame.weekday, Some(Weekday::Monday));
+        assert_eq!(frame.time.year(), 2216);
+        assert_eq!(frame.century, CenturyInference::Unique);
+
+        // Friday (101, lsb) and the date parity changes
+        signal[42..45].copy_from_slice(&[Bit::Value(true), Bit::Value(false), Bit::Value(true)]);
+        signal[58] = Bit::Value(true);
+        decoder.add_bit_ref_iter(signal.iter());
+        let frame = decoder.decode_frame()
+            .expect("Could not decode dcf77 time");
+        assert_eq!(frame.time.year(), 2016);
+        assert_eq!(frame.century, CenturyInference::Unique);
+
+        // The default just assumes 20xx
+        let mut decoder = Decoder::new();
+        decoder.add_bit_ref_iter(signal.iter());
+        let frame = decoder.decode_frame()
+            .expect("Could not decode dcf77 time");
+        assert_eq!(frame.time.year(), 2016);
+        assert_eq!(frame.century, CenturyInference::Configured);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_decoder_partial() {
+        let mut signal = build_valid_signal();
+        let mut decoder: Decoder = Decoder::new();
+        decoder.add_bit_ref_iter(signal.iter());
+
+        let partial = decoder.decode_partial();
+        assert!(partial.is_complete());
+        assert_eq!(partial.to_string(), "2016-01-01 00:00 MEZ");
+
+        // Break the date parity, time of day is still there
+        signal[50] = Bit::Value(true);
+        signal[29] = Bit::Unknown;
+        decoder.add_bit_ref_iter(signal.iter());
+        let partial = decoder.decode_partial();
+        assert_eq!(partial.minute, Some(0));
+        assert_eq!(partial.hour, None);
+        assert_eq!(partial.date, None);
+        assert_eq!(partial.timezone, Some(Dcf77Tz::MEZ));
+        assert_eq!(partial.to_string(), "????-??-?? ??:00 MEZ");
+        let failures: Vec<_> = partial.failures.iter()
+            .map(|e| e.decoding_failure())
+            .collect();
+        assert_eq!(failures, [
+            Some(DecodingFailure::MissingBit(29)),
+            Some(DecodingFailure::ParityError(ParityBitName::Date)),
+        ]);
+        // The full decoder gives up on the first
+        assert_matches!(decoder.decode_dcf77(),
+            Err(Error::DecodingError(DecodingFailure::MissingBit(29))));
+    }
+
+    #[cfg(all(feature = "std", feature = "serde"))]
+    #[test]
+    fn test_serde() {
+        let signal = build_valid_signal();
+        let mut decoder: Decoder = Decoder::new();
+        decoder.add_bit_ref_iter(signal.iter());
+        let frame = decoder.decode_frame().expect("Could not decode dcf77 time");
+        let json = serde_json::to_string(&frame).expect("Could not serialize");
+        assert_eq!(json, r#"{"time":"2016-01-01T00:00:00+01:00[+01:00]","zone_change":false,"leap_second":false,"weekday":1,"century":"Configured"}"#);
+        assert_eq!(serde_json::from_str::<Dcf77Frame>(&json).ok(), Some(frame));
+
+        let fields = Dcf77Fields::decode_bits(&signal).expect("Could not decode fields");
+        let json = serde_json::to_string(&fields).expect("Could not serialize");
+        assert!(json.starts_with(r#"{"meteotime":["?","?","?","?","?","?","?","?","?","?","?","?","?","?"],"call_bit":false"#));
+        assert_eq!(serde_json::from_str::<Dcf77Fields>(&json).ok(), Some(fields));
+
+        let failure = DecodingFailure::ParityError(ParityBitName::Date);
+        assert_eq!(serde_json::to_string(&failure).ok().as_deref(), Some(r#"{"ParityError":"Date"}"#));
+        assert_eq!(serde_json::to_string(&Error::from(failure)).ok().as_deref(),
+            Some(r#"{"DecodingError":{"ParityError":"Date"}}"#));
+        assert_eq!(serde_json::from_str::<Bit>(r#""_""#).ok(), Some(Bit::Skipped));
+        assert!(serde_json::from_str::<Bit>(r#""x""#).is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_decoder_render() {
+        let mut signal = build_valid_signal();
+        signal[37] = Bit::Value(true);
+        let mut decoder: Decoder = Decoder::new();
+        decoder.add_bit_ref_iter(signal.iter());
+
+        let rendered = decoder.render(RenderStyle::Plain);
+        let lines: Vec<&str> = rendered.lines().collect();
+        println!("{}", rendered);
+        assert_eq!(lines[0],
+            "M meteotime      R A1 Z1 Z2 A2 S min  10m P1 hour 10h P2 day  10d dow mon  10m year 10y  P3  _");
+        assert_eq!(lines[1],
+            "? ?????????????? 0 0  0  1  0  1 0000 000 0  0000 00  0  1100 00  100 1000 0   0110 1000 0   _");
+        assert_eq!(lines[2],
+            "                 0 0  0  1  0  1 0    0   ok 0    0   ok 3    0   1   1    0   6    1    ERR");
+        assert_eq!(lines[3].find('^'), Some(0));
+        assert_eq!(lines[3].find('x'), Some(lines[0].find("day").unwrap()));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_decoder_recorded_signals() {
+        for (recorded, expected) in [
+            ("01001011111101100010101100101011010110010111110000011001010_", "date parity error (bits 36-58)"),
+            ("01101100100001100010111100100011010110010010110000011101001_", "invalid year digit 14 (bits 50-57)"),
+            ("00001110011100100010110100001010100101011011111001011001001_", "minute parity error (bits 21-28)"),
+        ] {
+            let bits: BitString = recorded.parse().expect("Could not parse signal");
+            let mut decoder = Decoder::new();
+            decoder.add_bit_iter(bits.bits().iter().copied());
+            assert_eq!(format!("{:>60}", decoder), recorded);
+            let decoded = decoder.decode_dcf77()
+                .map_or_else(|e| e.to_string(), |time| time.to_string());
+            assert_eq!(decoded, expected);
+        }
     }
 
     /* TODO: Use some real-life examples:
@@ -0,0 +1,110 @@
+use std::fs::OpenOptions;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use jiff::{Timestamp, Zoned};
+use serde::Serialize;
+
+use crate::pulse_decoder::Pulse;
+use crate::time_code::DecodedFrame;
+
+/* Decoder events as JSON Lines, one object per line, for jq and log
+ * shippers:
+ *
+ *      {"schema":1,"timestamp":"2016-01-01T00:00:00.2Z","event":"bit","symbol":"0",...}
+ *
+ * `timestamp` is the system time of the event, for pulses and bits the time
+ * the pulse started. Fields are only ever added within a schema version.
+ */
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    // The received byte and what it measures as
+    Pulse { byte: u8, pulse: Pulse },
+    // The symbol the decoder classified the pulse as, and its time if locked
+    Bit { symbol: char, second: Option<&'a Zoned> },
+    Frame { frame: &'a DecodedFrame },
+    // `kind` is the `DecodingFailure` variant, `reason` the full message
+    Failure { kind: &'a str, reason: &'a str },
+    // Whether the time of each second is known, after a decoded frame
+    Sync { locked: bool }
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    schema: u32,
+    timestamp: Timestamp,
+    #[serde(flatten)]
+    event: &'a Event<'a>
+}
+
+pub struct EventLog {
+    out: Box<dyn Write>
+}
+
+impl EventLog {
+    pub fn new(out: impl Write + 'static) -> EventLog {
+        EventLog { out: Box::new(out) }
+    }
+
+    pub fn stdout() -> EventLog {
+        EventLog::new(io::stdout())
+    }
+
+    // Appends to an existing file
+    pub fn create(path: impl AsRef<Path>) -> io::Result<EventLog> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(EventLog::new(BufWriter::new(file)))
+    }
+
+    pub fn write(&mut self, timestamp: Timestamp, event: &Event) -> io::Result<()> {
+        let record = Record { schema: SCHEMA_VERSION, timestamp, event };
+        serde_json::to_writer(&mut self.out, &record)?;
+        self.out.write_all(b"\n")
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write() {
+        let buf = Shared::default();
+        let mut log = EventLog::new(buf.clone());
+        let timestamp: Timestamp = "2016-01-01T00:00:00.2Z".parse().unwrap();
+        log.write(timestamp, &Event::Pulse { byte: 0b1111_0000, pulse: Pulse::Single { width_ms: 100, saturated: false } }).unwrap();
+        log.write(timestamp, &Event::Failure { kind: "ParityError", reason: "Date parity error" }).unwrap();
+        log.write(timestamp, &Event::Sync { locked: true }).unwrap();
+
+        let text = String::from_utf8(buf.0.borrow().clone()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines, [
+            r#"{"schema":1,"timestamp":"2016-01-01T00:00:00.2Z","event":"pulse","byte":240,"pulse":{"kind":"single","width_ms":100,"saturated":false}}"#,
+            r#"{"schema":1,"timestamp":"2016-01-01T00:00:00.2Z","event":"failure","kind":"ParityError","reason":"Date parity error"}"#,
+            r#"{"schema":1,"timestamp":"2016-01-01T00:00:00.2Z","event":"sync","locked":true}"#
+        ]);
+    }
+}
@@ -0,0 +1,172 @@
+use core::ops::Range;
+#[cfg(feature = "std")]
+use std::fmt::Write;
+
+#[cfg(feature = "std")]
+use crate::bitrep::*;
+
+/* Annotated rendering of a time code frame: bits are grouped into fields,
+ * each with its label above and its decoded value below, e.g.
+ *
+ *      S min  10m P1
+ *      1 1010 000 1
+ *      1 5    0   ok
+ *
+ * Failed parity groups and unknown bits are highlighted, either
+ * with ANSI colors or, as plain text, with a marker line below.
+ */
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpanKind {
+    Bcd,        // One BCD digit, lsb first
+    BcdMsb,     // One BCD digit (or small binary number), msb first
+    Flag,
+    Parity,
+    Marker,     // Fixed or missing bits, value not shown
+    Raw         // Undecoded payload
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldSpan {
+    pub label: &'static str,
+    pub bits: Range<usize>,
+    pub kind: SpanKind
+}
+
+pub const fn span(label: &'static str, bits: Range<usize>, kind: SpanKind) -> FieldSpan {
+    FieldSpan { label, bits, kind }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Highlight {
+    ParityFailed,
+    Unknown
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderStyle {
+    Plain,
+    Ansi
+}
+
+#[cfg(feature = "std")]
+impl Highlight {
+    fn ansi_color(&self) -> &'static str {
+        match *self {
+            Self::ParityFailed  => "\x1b[31m",  // red
+            Self::Unknown       => "\x1b[33m",  // yellow
+        }
+    }
+
+    fn marker(&self) -> char {
+        match *self {
+            Self::ParityFailed  => 'x',
+            Self::Unknown       => '^',
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+const ANSI_RESET: &str = "\x1b[0m";
+
+#[cfg(feature = "std")]
+fn span_value(bits: &[Bit], span: &FieldSpan, highlights: &[Option<Highlight>]) -> String {
+    let slice = &bits[span.bits.clone()];
+    match span.kind {
+        SpanKind::Bcd | SpanKind::Flag => {
+            let value: Option<u32> = FromBits::from_maybebits_lsb(slice);
+            value.map_or(String::from("?"), |v| v.to_string())
+        }
+        SpanKind::BcdMsb => {
+            let value: Option<u32> = FromBits::from_maybebits_msb(slice);
+            value.map_or(String::from("?"), |v| v.to_string())
+        }
+        SpanKind::Parity => {
+            if slice.iter().any(|b| b.to_bit().is_none()) {
+                String::from("?")
+            } else if highlights[span.bits.start] == Some(Highlight::ParityFailed) {
+                String::from("ERR")
+            } else {
+                String::from("ok")
+            }
+        }
+        SpanKind::Marker | SpanKind::Raw => String::new()
+    }
+}
+
+/* Render the frame in three lines (label, bits, value), plus a marker line
+    for plain text if anything is highlighted. `highlights` has one entry
+    per bit.
+ */
+#[cfg(feature = "std")]
+pub fn render(bits: &[Bit], layout: &[FieldSpan], highlights: &[Option<Highlight>], style: RenderStyle) -> String {
+    assert_eq!(bits.len(), highlights.len());
+    let mut labels = String::new();
+    let mut values = String::new();
+    let mut bit_line = String::new();
+    let mut markers = String::new();
+
+    for span in layout.iter().filter(|span| span.bits.end <= bits.len()) {
+        let value = span_value(bits, span, highlights);
+        let width = span.label.len().max(span.bits.len()).max(value.len());
+        write!(labels, "{:<width$} ", span.label).unwrap();
+        write!(values, "{:<width$} ", value).unwrap();
+
+        for n in span.bits.clone() {
+            match (style, highlights[n]) {
+                (RenderStyle::Ansi, Some(h)) =>
+                    write!(bit_line, "{}{}{}", h.ansi_color(), bits[n], ANSI_RESET).unwrap(),
+                _ => write!(bit_line, "{}", bits[n]).unwrap(),
+            }
+            markers.push(highlights[n].map_or(' ', |h| h.marker()));
+        }
+        let padding = width + 1 - span.bits.len();
+        bit_line.extend(std::iter::repeat_n(' ', padding));
+        markers.extend(std::iter::repeat_n(' ', padding));
+    }
+
+    let mut result = String::new();
+    for line in [labels, bit_line, values] {
+        writeln!(result, "{}", line.trim_end()).unwrap();
+    }
+    if style == RenderStyle::Plain && highlights.iter().any(|h| h.is_some()) {
+        writeln!(result, "{}", markers.trim_end()).unwrap();
+    }
+    result
+}
+
+// Rendering needs std
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    const LAYOUT: [FieldSpan; 3] = [
+        FieldSpan { label: "S", bits: 0..1, kind: SpanKind::Flag },
+        FieldSpan { label: "min", bits: 1..5, kind: SpanKind::Bcd },
+        FieldSpan { label: "P1", bits: 5..6, kind: SpanKind::Parity },
+    ];
+
+    #[test]
+    fn test_render_plain() {
+        let bits = [true, true, false, true, false, true].map(Bit::Value);
+        let highlights = [None; 6];
+        assert_eq!(render(&bits, &LAYOUT, &highlights, RenderStyle::Plain),
+            "S min  P1\n1 1010 1\n1 5    ok\n");
+
+        let mut bits = bits;
+        bits[2] = Bit::Unknown;
+        let highlights = [None, None, Some(Highlight::Unknown), None, None, Some(Highlight::ParityFailed)];
+        assert_eq!(render(&bits, &LAYOUT, &highlights, RenderStyle::Plain),
+            "S min  P1\n1 1?10 1\n1 ?    ERR\n   ^   x\n");
+    }
+
+    #[test]
+    fn test_render_ansi() {
+        let bits = [true, true, false, true, false, true].map(Bit::Value);
+        let mut highlights = [None; 6];
+        highlights[5] = Some(Highlight::ParityFailed);
+        let rendered = render(&bits, &LAYOUT, &highlights, RenderStyle::Ansi);
+        assert_eq!(rendered.lines().nth(1), Some("1 1010 \x1b[31m1\x1b[0m"));
+        assert_eq!(rendered.lines().count(), 3);
+    }
+}
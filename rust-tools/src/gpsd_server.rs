@@ -0,0 +1,254 @@
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+
+use jiff::Zoned;
+use serde_json::{Value, json};
+
+use crate::time_code::RefclockSample;
+
+/* A small gpsd, speaking its JSON protocol to existing clients (cgps,
+ * dashboards, ...). Clients get a VERSION on connect and ask for reports
+ * with ?WATCH={"enable":true,"json":true}. Watchers get
+ *
+ *      TPV     every second: mode 1 (time, but no position) with the time
+ *              while the decoder is locked, mode 0 without
+ *      TOFF    every minute mark: its time and the system time we received
+ *              it at
+ *
+ * There are no threads: `poll` accepts clients and answers their requests
+ * without blocking, it is called from the read loop. Clients that can't
+ * keep up are dropped.
+ */
+
+pub const DEFAULT_PORT: u16 = 2947;
+
+const PROTO_MAJOR: u32 = 3;
+const PROTO_MINOR: u32 = 15;
+
+struct Client {
+    stream: TcpStream,
+    input: Vec<u8>,
+    watching: bool,
+    closed: bool
+}
+
+impl Client {
+    fn send(&mut self, message: &Value) {
+        let line = format!("{}\r\n", message);
+        if self.stream.write_all(line.as_bytes()).is_err() {
+            self.closed = true;
+        }
+    }
+}
+
+pub struct GpsdServer {
+    listener: TcpListener,
+    clients: Vec<Client>,
+    // The serial port and time code, as DEVICE
+    path: String,
+    driver: String,
+    precision: i32
+}
+
+impl GpsdServer {
+    pub fn bind(address: impl ToSocketAddrs, path: &str, driver: &str, precision: i32) -> io::Result<GpsdServer> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(GpsdServer {
+            listener,
+            clients: Vec::new(),
+            path: path.to_string(),
+            driver: driver.to_uppercase(),
+            precision
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // Accept new clients and answer their requests
+    pub fn poll(&mut self) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_err() {
+                continue;
+            }
+            let mut client = Client { stream, input: Vec::new(), watching: false, closed: false };
+            client.send(&self.version());
+            self.clients.push(client);
+        }
+        for n in 0..self.clients.len() {
+            let mut buf = [0u8; 512];
+            loop {
+                match self.clients[n].stream.read(&mut buf) {
+                    Ok(0) => {
+                        self.clients[n].closed = true;
+                        break;
+                    }
+                    Ok(len) => self.clients[n].input.extend_from_slice(&buf[..len]),
+                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(_) => {
+                        self.clients[n].closed = true;
+                        break;
+                    }
+                }
+            }
+            // Requests end with a semicolon or newline
+            while let Some(end) = self.clients[n].input.iter().position(|c| *c == b';' || *c == b'\n') {
+                let request: Vec<u8> = self.clients[n].input.drain(..=end).collect();
+                let request = String::from_utf8_lossy(&request[..end]).trim().to_string();
+                if !request.is_empty() {
+                    self.handle_request(n, &request);
+                }
+            }
+        }
+        self.clients.retain(|client| !client.closed);
+    }
+
+    fn handle_request(&mut self, n: usize, request: &str) {
+        let (command, argument) = request.split_once('=').unwrap_or((request, ""));
+        match command {
+            "?VERSION" => {
+                let version = self.version();
+                self.clients[n].send(&version);
+            }
+            "?DEVICES" => {
+                let devices = self.devices();
+                self.clients[n].send(&devices);
+            }
+            "?WATCH" => {
+                let watch: Value = if argument.is_empty() { json!({}) } else {
+                    match serde_json::from_str(argument) {
+                        Ok(watch) => watch,
+                        Err(e) => {
+                            self.clients[n].send(&error(&format!("Invalid WATCH: {}", e)));
+                            return
+                        }
+                    }
+                };
+                if let Some(enable) = watch.get("enable").and_then(Value::as_bool) {
+                    self.clients[n].watching = enable;
+                } else if !argument.is_empty() {
+                    self.clients[n].watching = true;
+                }
+                if self.clients[n].watching {
+                    let devices = self.devices();
+                    self.clients[n].send(&devices);
+                }
+                let enable = self.clients[n].watching;
+                self.clients[n].send(&json!({"class": "WATCH", "enable": enable, "json": enable}));
+            }
+            _ => self.clients[n].send(&error(&format!("Unrecognized request '{}'", command)))
+        }
+    }
+
+    fn version(&self) -> Value {
+        json!({
+            "class": "VERSION",
+            "release": env!("CARGO_PKG_VERSION"),
+            "rev": concat!("rust-tools ", env!("CARGO_PKG_VERSION")),
+            "proto_major": PROTO_MAJOR,
+            "proto_minor": PROTO_MINOR
+        })
+    }
+
+    fn devices(&self) -> Value {
+        json!({
+            "class": "DEVICES",
+            "devices": [{"class": "DEVICE", "path": self.path, "driver": self.driver}]
+        })
+    }
+
+    fn broadcast(&mut self, message: &Value) {
+        for client in self.clients.iter_mut().filter(|client| client.watching) {
+            client.send(message);
+        }
+        self.clients.retain(|client| !client.closed);
+    }
+
+    // Once a second, the time if locked
+    pub fn report_time(&mut self, time: Option<&Zoned>) {
+        let tpv = match time {
+            Some(time) => json!({
+                "class": "TPV",
+                "device": self.path,
+                "mode": 1,
+                "time": format!("{:.3}", time.timestamp()),
+                "ept": precision_seconds(self.precision)
+            }),
+            None => json!({"class": "TPV", "device": self.path, "mode": 0})
+        };
+        self.broadcast(&tpv);
+    }
+
+    pub fn report_offset(&mut self, sample: &RefclockSample) {
+        let toff = json!({
+            "class": "TOFF",
+            "device": self.path,
+            "real_sec": sample.clock.as_second(),
+            "real_nsec": sample.clock.subsec_nanosecond(),
+            "clock_sec": sample.receive.as_second(),
+            "clock_nsec": sample.receive.subsec_nanosecond(),
+            "precision": self.precision
+        });
+        self.broadcast(&toff);
+    }
+}
+
+fn error(message: &str) -> Value {
+    json!({"class": "ERROR", "message": message})
+}
+
+fn precision_seconds(precision: i32) -> f64 {
+    2f64.powi(precision)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader};
+    use std::time::Duration;
+    use jiff::Timestamp;
+    use crate::time_code::LeapSecond;
+    use super::*;
+
+    fn read_report(reader: &mut BufReader<TcpStream>) -> Value {
+        let mut line = String::new();
+        reader.read_line(&mut line).expect("No report");
+        serde_json::from_str(&line).expect("Report is not JSON")
+    }
+
+    #[test]
+    fn test_watch() {
+        let mut server = GpsdServer::bind("127.0.0.1:0", "/dev/ttyAMA0", "dcf77", -6)
+            .expect("Could not bind");
+        let stream = TcpStream::connect(server.local_addr().unwrap()).expect("Could not connect");
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        server.poll();
+        assert_eq!(read_report(&mut reader)["class"], "VERSION");
+
+        // Not watching yet
+        let time: Zoned = "2016-01-01T00:00:00+01:00[+01:00]".parse().unwrap();
+        server.report_time(Some(&time));
+        (&stream).write_all(b"?WATCH={\"enable\":true,\"json\":true};\n").unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        server.poll();
+        assert_eq!(read_report(&mut reader)["devices"][0]["driver"], "DCF77");
+        assert_eq!(read_report(&mut reader), json!({"class": "WATCH", "enable": true, "json": true}));
+
+        server.report_time(Some(&time));
+        let tpv = read_report(&mut reader);
+        assert_eq!((&tpv["class"], &tpv["mode"]), (&json!("TPV"), &json!(1)));
+        assert_eq!(tpv["time"], "2015-12-31T23:00:00.000Z");
+
+        let receive: Timestamp = "2015-12-31T23:00:00.25Z".parse().unwrap();
+        server.report_offset(&RefclockSample { clock: time.timestamp(), receive, leap_second: LeapSecond::None });
+        let toff = read_report(&mut reader);
+        assert_eq!((&toff["real_nsec"], &toff["clock_nsec"]), (&json!(0), &json!(250_000_000)));
+
+        (&stream).write_all(b"?FOO;").unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        server.poll();
+        assert_eq!(read_report(&mut reader)["class"], "ERROR");
+    }
+}
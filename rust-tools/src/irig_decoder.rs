@@ -0,0 +1,431 @@
+use std::convert::Infallible;
+use std::fmt::{self};
+use std::ops::Range;
+
+use jiff::{SignedDuration, Zoned, civil::Date, tz};
+
+use crate::bit_frame::{BitFrame, BitRange, FrameError};
+use crate::bitrep::*;
+use crate::century::CenturyInference;
+use crate::frame_view::FieldSpan;
+use crate::marker_sync::{MarkerLayout, MarkerSymbol, MarkerSync, marker_bits};
+use crate::pulse_decoder::{Pulse, PulseTiming, WidthClasses};
+use crate::time_code::{self, DecodeError, DecodedFrame, LeapSecond, StationFailure, TimeCodeDecoder, decode_field, decode_value, symbol_char};
+
+/* IRIG-B000 (IRIG Standard 200), pulse width coded, unmodulated.
+ *
+ * 100 pulses per second, each starting at the full 10ms boundary:
+ *      2ms         0
+ *      5ms         1
+ *      8ms         marker (Pr at 0, P1 - P9 at 9, 19, ... 89, P0 at 99)
+ * The frame gives the (usually UTC) time at the leading edge of Pr, and
+ * P0 directly followed by Pr marks the start of a frame.
+ *
+ * Bit          Contents
+ * 0            Pr (reference marker)
+ * 1  - 8       BCD (lsb first) Seconds: 1 2 4 8 0 10 20 40
+ * 10 - 17      BCD (lsb first) Minutes: 1 2 4 8 0 10 20 40
+ * 20 - 26      BCD (lsb first) Hours: 1 2 4 8 0 10 20
+ * 30 - 41      BCD (lsb first) Day of year: 1 2 4 8 0 10 20 40 80 P4 100 200
+ * 50 - 78      Control functions (27 bits, without the markers)
+ * 80 - 97      Straight binary seconds of the day (lsb first, 17 bits, without P9)
+ * All other bits are 0. B000 carries no year, so it has to be configured
+ * (and changed at new year).
+ *
+ * Pulses up to 8ms need 1000 baud (1ms per bit, saturating at 9ms, just
+ * leaving the stop bit before the next pulse).
+ */
+pub const PULSE_TIMING: PulseTiming = PulseTiming { bit_ms: 1, period_ms: 10 };
+
+pub const LAYOUT: MarkerLayout = MarkerLayout {
+    len: 100,
+    markers: &[0, 9, 19, 29, 39, 49, 59, 69, 79, 89, 99]
+};
+
+// The markers and unused bits are taken out of the fields (`skip`)
+#[allow(clippy::duplicated_attributes)]
+#[derive(Clone, Debug, PartialEq, BitFrame)]
+#[frame(len = 100)]
+#[fixed(name = "reference", bits = 0, label = "M", skipped)]
+#[fixed(name = "p1", bits = 9, label = "P1", skipped)]
+#[fixed(name = "p2", bits = 19, label = "P2", skipped)]
+#[fixed(name = "p3", bits = 29, label = "P3", skipped)]
+#[fixed(name = "p4", bits = 39, label = "P4", skipped)]
+#[fixed(name = "p5", bits = 49, label = "P5", skipped)]
+#[fixed(name = "p6", bits = 59, label = "P6", skipped)]
+#[fixed(name = "p7", bits = 69, label = "P7", skipped)]
+#[fixed(name = "p8", bits = 79, label = "P8", skipped)]
+#[fixed(name = "p9", bits = 89, label = "P9", skipped)]
+#[fixed(name = "p0", bits = 99, label = "P0", skipped)]
+#[fixed(name = "unused", bits = [5, 14, 18, 24, 27..29, 34, 42..49, 98], label = "_")]
+pub struct IrigFields {
+    #[field(bits = 1..9, skip = [5], bcd = [4, 3], labels = ["sec", "10s"])]
+    pub second: i16,
+    #[field(bits = 10..18, skip = [14], bcd = [4, 3], labels = ["min", "10m"])]
+    pub minute: i16,
+    #[field(bits = 20..27, skip = [24], bcd = [4, 2], labels = ["hour", "10h"])]
+    pub hour: i16,
+    #[field(bits = 30..42, skip = [34, 39], bcd = [4, 4, 2], labels = ["day", "10d", "100d"])]
+    pub day_of_year: i16,
+    #[field(bits = 50..79, skip = [59, 69], binary, label = "cf")]
+    pub control_functions: u32,
+    #[field(bits = 80..98, skip = [89], binary, label = "sbs")]
+    pub straight_binary_seconds: u32
+}
+
+const PULSE_CLASSES: WidthClasses<MarkerSymbol> = WidthClasses {
+    classes: &[
+        (3, MarkerSymbol::Zero),
+        (6, MarkerSymbol::One),
+    ],
+    longer: MarkerSymbol::Marker
+};
+
+pub fn decode_pulse(pulse: u8) -> MarkerSymbol {
+    match PULSE_TIMING.measure(pulse) {
+        Pulse::None => MarkerSymbol::Skipped,
+        Pulse::Single { width_ms, .. } => PULSE_CLASSES.classify(width_ms),
+        _ => MarkerSymbol::Unknown
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    Second,
+    Minute,
+    Hour,
+    DayOfYear,
+    ControlFunctions,
+    StraightBinarySeconds
+}
+
+impl Field {
+    pub fn bit_range(&self) -> Range<usize> {
+        match *self {
+            Self::Second                => IrigFields::SECOND_BITS,
+            Self::Minute                => IrigFields::MINUTE_BITS,
+            Self::Hour                  => IrigFields::HOUR_BITS,
+            Self::DayOfYear             => IrigFields::DAY_OF_YEAR_BITS,
+            Self::ControlFunctions      => IrigFields::CONTROL_FUNCTIONS_BITS,
+            Self::StraightBinarySeconds => IrigFields::STRAIGHT_BINARY_SECONDS_BITS,
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match *self {
+            Self::Second                => "second",
+            Self::Minute                => "minute",
+            Self::Hour                  => "hour",
+            Self::DayOfYear             => "day of year",
+            Self::ControlFunctions      => "control functions",
+            Self::StraightBinarySeconds => "straight binary seconds",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodingFailure {
+    NotEnoughBits,
+    NotSync,    /* Markers are not where they belong */
+    UnknownYear,
+    MissingBit(usize),
+    UnusedBitSet(usize),
+    SecondsMismatch(u32),   /* Straight binary seconds disagree with the BCD time */
+    InvalidDigit(Field, u8),
+    OutOfRange(Field, i16)
+}
+
+impl DecodingFailure {
+    pub const KINDS: [&'static str; 8] = ["NotEnoughBits", "NotSync", "UnknownYear", "MissingBit", "UnusedBitSet", "SecondsMismatch", "InvalidDigit", "OutOfRange"];
+}
+
+impl StationFailure for DecodingFailure {
+    type Field = Field;
+    type Parity = Infallible;
+
+    fn kind(&self) -> &'static str {
+        match *self {
+            Self::NotEnoughBits      => "NotEnoughBits",
+            Self::NotSync            => "NotSync",
+            Self::UnknownYear        => "UnknownYear",
+            Self::MissingBit(_)      => "MissingBit",
+            Self::UnusedBitSet(_)    => "UnusedBitSet",
+            Self::SecondsMismatch(_) => "SecondsMismatch",
+            Self::InvalidDigit(..)   => "InvalidDigit",
+            Self::OutOfRange(..)     => "OutOfRange",
+        }
+    }
+
+    fn is_not_sync(&self) -> bool {
+        *self == Self::NotSync
+    }
+
+    fn field_failure(field: Field, error: FrameError<Infallible>) -> Self {
+        match error {
+            FrameError::NotEnoughBits           => Self::NotEnoughBits,
+            FrameError::NotSync                 => Self::NotSync,
+            FrameError::MissingBit(n)           => Self::MissingBit(n),
+            FrameError::ParityError(never)      => match never {},
+            FrameError::InvalidDigit(_, value)  => Self::InvalidDigit(field, value),
+            FrameError::InvalidValue(_, value)  => Self::OutOfRange(field, value as i16),
+        }
+    }
+
+    fn out_of_range(field: Field, value: i16) -> Self {
+        Self::OutOfRange(field, value)
+    }
+}
+
+impl fmt::Display for DecodingFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::NotEnoughBits     => write!(f, "not enough bits for a full frame"),
+            Self::NotSync           => write!(f, "position markers out of place"),
+            Self::UnknownYear       => write!(f, "year not configured, B000 carries none"),
+            Self::MissingBit(n)     => write!(f, "bit {} is missing", n),
+            Self::UnusedBitSet(n)   => write!(f, "unused bit {} is set", n),
+            Self::SecondsMismatch(s) => write!(f, "straight binary seconds {} do not match the time", s),
+            Self::InvalidDigit(field, v) =>
+                write!(f, "invalid {} digit {} ({})", field, v, BitRange(field.bit_range())),
+            Self::OutOfRange(field, v) =>
+                write!(f, "{} {} out of range ({})", field, v, BitRange(field.bit_range())),
+        }
+    }
+}
+
+pub type Error = DecodeError<DecodingFailure>;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IrigFrame {
+    // Leading edge of the reference marker
+    pub time: Zoned,
+    pub control_functions: u32,
+    // Not sent by all generators (B001, B002)
+    pub straight_binary_seconds: Option<u32>
+}
+
+pub struct Decoder {
+    sync: MarkerSync,
+    year: Option<i16>
+}
+
+impl fmt::Display for Decoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.sync.fmt(f)
+    }
+}
+
+impl Decoder {
+    // Frames don't decode until the year is configured
+    pub fn new() -> Decoder {
+        Decoder {
+            sync: MarkerSync::new(LAYOUT),
+            year: None
+        }
+    }
+
+    pub fn with_year(mut self, year: i16) -> Decoder {
+        self.year = Some(year);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.sync.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.sync.is_full()
+    }
+
+    pub fn add_symbol(&mut self, value: MarkerSymbol) -> &Self {
+        self.sync.add_symbol(value);
+        self
+    }
+
+    pub fn add_pulse(&mut self, pulse: u8) -> &Self {
+        self.add_symbol(decode_pulse(pulse))
+    }
+
+    pub fn add_symbol_iter(&mut self, iter: impl IntoIterator<Item = MarkerSymbol>) -> &Self {
+        for symbol in iter {
+            self.add_symbol(symbol);
+        }
+        self
+    }
+
+    pub fn decode_irig(&self) -> Result<Zoned> {
+        self.decode_frame().map(|frame| frame.time)
+    }
+
+    pub fn decode_frame(&self) -> Result<IrigFrame> {
+        if !self.sync.is_full() {
+            return Err(From::from(DecodingFailure::NotEnoughBits))
+        }
+        let frame = self.sync.frame()
+            .ok_or(DecodingFailure::NotSync)?;
+        let bits = marker_bits(&frame);
+
+        for n in IrigFields::UNUSED_BITS {
+            if bits[*n].to_bit() == Some(true) {
+                return Err(From::from(DecodingFailure::UnusedBitSet(*n)))
+            }
+        }
+
+        let second = decode_field(Field::Second, IrigFields::decode_second(&bits), 0..61)?;
+        let minute = decode_field(Field::Minute, IrigFields::decode_minute(&bits), 0..60)?;
+        let hour = decode_field(Field::Hour, IrigFields::decode_hour(&bits), 0..24)?;
+        let day_of_year = decode_field(Field::DayOfYear, IrigFields::decode_day_of_year(&bits), 1..367)?;
+        let control_functions = decode_value(Field::ControlFunctions, IrigFields::decode_control_functions(&bits))?;
+        let seconds = decode_value(Field::StraightBinarySeconds, IrigFields::decode_straight_binary_seconds(&bits))?;
+        let straight_binary_seconds = match seconds {
+            0 => None,
+            s if s == hour as u32 * 3600 + minute as u32 * 60 + second as u32 => Some(s),
+            s => return Err(From::from(DecodingFailure::SecondsMismatch(s)))
+        };
+
+        let year = self.year.ok_or(DecodingFailure::UnknownYear)?;
+        let date = Date::new(year, 1, 1)?
+            .with()
+            .day_of_year(day_of_year)
+            .build()
+            .map_err(|_| DecodingFailure::OutOfRange(Field::DayOfYear, day_of_year))?;
+        // A leap second (60) is folded into the following second
+        let time = date.at(hour as i8, minute as i8, 0, 0)
+            .to_zoned(tz::TimeZone::UTC)?
+            .checked_add(SignedDuration::from_secs(second as i64))?;
+        Ok(IrigFrame { time, control_functions, straight_binary_seconds })
+    }
+}
+
+impl TimeCodeDecoder for Decoder {
+    fn name(&self) -> &'static str {
+        "irig-b"
+    }
+
+    fn pulse_timing(&self) -> PulseTiming {
+        PULSE_TIMING
+    }
+
+    fn failure_kinds(&self) -> Vec<&'static str> {
+        time_code::failure_kinds(&DecodingFailure::KINDS)
+    }
+
+    fn pulse_thresholds(&self) -> Vec<(u32, char)> {
+        time_code::width_thresholds(&PULSE_CLASSES)
+    }
+
+    fn frame_len(&self) -> usize {
+        LAYOUT.len
+    }
+
+    fn alphabet(&self) -> &'static str {
+        "01M?_"
+    }
+
+    fn layout(&self) -> &'static [FieldSpan] {
+        IrigFields::LAYOUT
+    }
+
+    fn feed_pulse(&mut self, pulse: u8) -> char {
+        let symbol = decode_pulse(pulse);
+        self.add_symbol(symbol);
+        symbol_char(symbol)
+    }
+
+    fn feed_symbol(&mut self, symbol: char) -> bool {
+        let Some(symbol) = MarkerSymbol::from_char(symbol) else { return false };
+        self.add_symbol(symbol);
+        true
+    }
+
+    fn is_synced(&self) -> bool {
+        self.sync.is_aligned()
+    }
+
+    fn frame_bits(&self) -> Vec<Bit> {
+        marker_bits(&self.sync.symbols())
+    }
+
+    // The frame gives the second it was sent in
+    fn decode(&self) -> time_code::Result<DecodedFrame> {
+        let frame = self.decode_frame()?;
+        let next_frame = frame.time.checked_add(SignedDuration::from_secs(1))
+            .map_err(Error::from)?;
+        Ok(DecodedFrame {
+            time: frame.time,
+            next_frame,
+            century: CenturyInference::Configured,
+            leap_second: LeapSecond::None,
+            summer_time: false,
+            summer_time_change: false
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::marker_sync::marker_symbols;
+    use MarkerSymbol::*;
+
+    fn build_signal(straight_binary_seconds: u32) -> Vec<MarkerSymbol> {
+        let fields = IrigFields {
+            second: 58,
+            minute: 59,
+            hour: 23,
+            day_of_year: 366,
+            control_functions: 0b100,
+            straight_binary_seconds
+        };
+        marker_symbols(&fields.encode_bits().expect("Could not encode the frame"))
+    }
+
+    // 2024-12-31 (day 366) 23:59:58 UTC
+    fn build_valid_signal() -> Vec<MarkerSymbol> {
+        build_signal(86398)
+    }
+
+    #[test]
+    fn test_decode_pulse() {
+        assert_eq!(decode_pulse(0b1), Zero);
+        assert_eq!(decode_pulse(0b1111), One);
+        assert_eq!(decode_pulse(0b111_1111), Marker);
+        assert_eq!(decode_pulse(0xFF), Marker);
+        assert_eq!(decode_pulse(0b0), Skipped);
+    }
+
+    #[test]
+    fn test_decoder_valid_signal() {
+        let mut signal = build_valid_signal();
+        let mut decoder = Decoder::new().with_year(2024);
+        decoder.add_symbol_iter(signal[50..].iter().copied());
+        decoder.add_symbol_iter(signal.iter().copied());
+        let frame = decoder.decode_frame()
+            .expect("Could not decode irig time");
+        assert_eq!(frame.time.to_string(), "2024-12-31T23:59:58+00:00[UTC]");
+        assert_eq!(frame.control_functions, 0b100);
+        assert_eq!(frame.straight_binary_seconds, Some(86398));
+        let mut unconfigured = Decoder::new();
+        unconfigured.add_symbol_iter(signal[50..].iter().chain(&signal).copied());
+        assert!(matches!(unconfigured.decode_irig(),
+            Err(Error::DecodingError(DecodingFailure::UnknownYear))));
+        // 2023 had no day 366
+        let mut common_year = Decoder::new().with_year(2023);
+        common_year.add_symbol_iter(signal[50..].iter().chain(&signal).copied());
+        let error = common_year.decode_irig().unwrap_err();
+        assert_eq!(error.to_string(), "day of year 366 out of range (bits 30-41)");
+
+        // Straight binary seconds are optional, but have to match
+        signal[80] = One;
+        decoder.add_symbol_iter(signal.iter().copied());
+        assert!(matches!(decoder.decode_irig(),
+            Err(Error::DecodingError(DecodingFailure::SecondsMismatch(86399)))));
+        decoder.add_symbol_iter(build_signal(0));
+        assert_eq!(decoder.decode_frame().map(|f| f.straight_binary_seconds).ok(), Some(None));
+    }
+}
@@ -0,0 +1,576 @@
+use std::convert::Infallible;
+use std::fmt::{self};
+use std::ops::Range;
+
+use jiff::{SignedDuration, Zoned, civil::{Date, Weekday}, tz};
+
+use crate::bit_frame::{BitEnum, BitFrame, BitRange, FrameError};
+use crate::bitrep::*;
+use crate::century::{CenturyInference, CenturyPolicy};
+use crate::frame_view::FieldSpan;
+use crate::marker_sync::{MarkerLayout, MarkerSymbol, MarkerSync, marker_bits};
+use crate::pulse_decoder::{Pulse, PulseTiming, WidthClasses};
+use crate::time_code::{self, DecodeError, DecodedFrame, StationFailure, TimeCodeDecoder, decode_field, decode_value, symbol_char};
+pub use crate::time_code::LeapSecond;
+
+/* JJY (Japan, 40 kHz / 60 kHz), see e.g. https://en.wikipedia.org/wiki/JJY
+ *
+ * The carrier is at full power at the start of every second, for 800ms (0),
+ * 500ms (1) or 200ms (marker), and reduced for the rest of it. Receivers
+ * output the reduced carrier as the pulse, like for DCF77 and WWVB:
+ *      200ms       0
+ *      500ms       1
+ *      800ms       marker (seconds 0, 9, 19, ... 59)
+ * Like WWVB, the code gives the (JST, UTC+9) time of the minute it is
+ * transmitted in.
+ *
+ * Second       Contents
+ * 0            frame reference marker
+ * 1  - 8       BCD (msb first) Minutes: 40 20 10 0 8 4 2 1
+ * 9            P1
+ * 12 - 18      BCD (msb first) Hours: 20 10 0 8 4 2 1
+ * 19           P2
+ * 22 - 28      BCD (msb first) Day of year: 200 100 0 80 40 20 10
+ * 29           P3
+ * 30 - 33      BCD (msb first) Day of year: 8 4 2 1
+ * 36           PA1: even parity over the hours
+ * 37           PA2: even parity over the minutes
+ * 38           SU1 (spare)
+ * 39           P4
+ * 40           SU2 (spare)
+ * 41 - 48      BCD (msb first) Years: 80 40 20 10 8 4 2 1
+ * 49           P5
+ * 50 - 52      Day of week: 0 = Sunday ... 6 = Saturday
+ * 53 - 54      Leap second: 00 none, 11 inserted, 10 deleted at the end of the month
+ * 59           P0
+ * All other seconds are always 0.
+ *
+ * In minutes 15 and 45 the call sign is sent in morse code during seconds
+ * 40 - 48, and seconds 50 - 55 carry service interruption notices instead of
+ * the day of week and the leap second. The year is then taken from the last
+ * complete frame.
+ *
+ * Pulses up to 800ms need 10 baud (100ms per bit, saturating at 900ms).
+ */
+pub const PULSE_TIMING: PulseTiming = PulseTiming { bit_ms: 100, period_ms: 1000 };
+
+pub const LAYOUT: MarkerLayout = MarkerLayout {
+    len: 60,
+    markers: &[0, 9, 19, 29, 39, 49, 59]
+};
+
+// The markers and unused seconds are taken out of the fields (`skip`)
+#[allow(clippy::duplicated_attributes)]
+#[derive(Clone, Debug, PartialEq, BitFrame)]
+#[frame(len = 60)]
+#[fixed(name = "frame_reference", bits = 0, label = "M", skipped)]
+#[fixed(name = "p1", bits = 9, label = "P1", skipped)]
+#[fixed(name = "p2", bits = 19, label = "P2", skipped)]
+#[fixed(name = "p3", bits = 29, label = "P3", skipped)]
+#[fixed(name = "p4", bits = 39, label = "P4", skipped)]
+#[fixed(name = "p5", bits = 49, label = "P5", skipped)]
+#[fixed(name = "p0", bits = 59, label = "P0", skipped)]
+#[fixed(name = "unused", bits = [4, 10..12, 14, 20..22, 24, 34..36], label = "_")]
+#[fixed(name = "spare", bits = 55..59, label = "_")]
+pub struct JjyFields {
+    #[field(bits = 1..9, skip = [4], bcd = [3, 4], msb, labels = ["10m", "min"])]
+    pub minute: i16,
+    #[field(bits = 12..19, skip = [14], bcd = [2, 4], msb, labels = ["10h", "hour"])]
+    pub hour: i16,
+    #[field(bits = 22..34, skip = [24, 29], bcd = [2, 4, 4], msb, labels = ["100d", "10d", "day"])]
+    pub day_of_year: i16,
+    #[field(bits = 36, label = "PA1")]
+    pub pa1: bool,
+    #[field(bits = 37, label = "PA2")]
+    pub pa2: bool,
+    #[field(bits = 38, label = "SU1")]
+    pub su1: bool,
+    #[field(bits = 40, label = "SU2")]
+    pub su2: bool,
+    #[field(bits = 41..49, bcd = [4, 4], msb, labels = ["10y", "year"])]
+    pub year: i16,
+    #[field(bits = 50..53, bcd = [3], msb, labels = ["dow"])]
+    pub weekday: i16,
+    #[field(bits = 53..55, enumeration, msb, label = "LS")]
+    pub leap_second: LeapSecond
+}
+
+// In the call sign minutes, the service notices take second 55 as well
+const UNUSED_BITS_CALL_SIGN: Range<usize> = 56..59;
+const CALL_SIGN_SECONDS: Range<usize> = 40..49;
+
+const PULSE_CLASSES: WidthClasses<MarkerSymbol> = WidthClasses {
+    classes: &[
+        (350, MarkerSymbol::Zero),
+        (650, MarkerSymbol::One),
+    ],
+    longer: MarkerSymbol::Marker
+};
+
+pub fn decode_pulse(pulse: u8) -> MarkerSymbol {
+    match PULSE_TIMING.measure(pulse) {
+        Pulse::None => MarkerSymbol::Skipped,
+        Pulse::Single { width_ms, .. } => PULSE_CLASSES.classify(width_ms),
+        _ => MarkerSymbol::Unknown
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    Minute,
+    Hour,
+    DayOfYear,
+    Year,
+    Weekday,
+    LeapSecond
+}
+
+impl Field {
+    pub fn bit_range(&self) -> Range<usize> {
+        match *self {
+            Self::Minute        => JjyFields::MINUTE_BITS,
+            Self::Hour          => JjyFields::HOUR_BITS,
+            Self::DayOfYear     => JjyFields::DAY_OF_YEAR_BITS,
+            Self::Year          => JjyFields::YEAR_BITS,
+            Self::Weekday       => JjyFields::WEEKDAY_BITS,
+            Self::LeapSecond    => JjyFields::LEAP_SECOND_BITS,
+        }
+    }
+
+    // The bits of the field and the bit holding their (even) parity
+    fn parity(&self) -> Option<(Range<usize>, usize)> {
+        match *self {
+            Self::Minute    => Some((JjyFields::MINUTE_BITS, JjyFields::PA2_BITS.start)),
+            Self::Hour      => Some((JjyFields::HOUR_BITS, JjyFields::PA1_BITS.start)),
+            _               => None,
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match *self {
+            Self::Minute        => "minute",
+            Self::Hour          => "hour",
+            Self::DayOfYear     => "day of year",
+            Self::Year          => "year",
+            Self::Weekday       => "day of week",
+            Self::LeapSecond    => "leap second",
+        })
+    }
+}
+
+impl BitEnum for LeapSecond {
+    fn from_bit_value(value: u32) -> Option<Self> {
+        match value {
+            0b00 => Some(Self::None),
+            0b11 => Some(Self::Inserted),
+            0b10 => Some(Self::Deleted),
+            _ => None
+        }
+    }
+
+    fn to_bit_value(&self) -> u32 {
+        match *self {
+            Self::None      => 0b00,
+            Self::Inserted  => 0b11,
+            Self::Deleted   => 0b10,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodingFailure {
+    NotEnoughBits,
+    NotSync,    /* Markers are not where they belong */
+    MissingBit(usize),
+    UnusedBitSet(usize),
+    ParityError(Field),
+    InvalidLeapSecond,
+    UnknownYear,    /* Call sign minute without a previous complete frame */
+    InvalidDigit(Field, u8),
+    OutOfRange(Field, i16)
+}
+
+impl DecodingFailure {
+    pub const KINDS: [&'static str; 9] = ["NotEnoughBits", "NotSync", "MissingBit", "UnusedBitSet", "ParityError", "InvalidLeapSecond", "UnknownYear", "InvalidDigit", "OutOfRange"];
+}
+
+impl StationFailure for DecodingFailure {
+    type Field = Field;
+    type Parity = Infallible;
+
+    fn kind(&self) -> &'static str {
+        match *self {
+            Self::NotEnoughBits     => "NotEnoughBits",
+            Self::NotSync           => "NotSync",
+            Self::MissingBit(_)     => "MissingBit",
+            Self::UnusedBitSet(_)   => "UnusedBitSet",
+            Self::ParityError(_)    => "ParityError",
+            Self::InvalidLeapSecond => "InvalidLeapSecond",
+            Self::UnknownYear       => "UnknownYear",
+            Self::InvalidDigit(..)  => "InvalidDigit",
+            Self::OutOfRange(..)    => "OutOfRange",
+        }
+    }
+
+    fn is_not_sync(&self) -> bool {
+        *self == Self::NotSync
+    }
+
+    fn field_failure(field: Field, error: FrameError<Infallible>) -> Self {
+        match error {
+            FrameError::NotEnoughBits           => Self::NotEnoughBits,
+            FrameError::NotSync                 => Self::NotSync,
+            FrameError::MissingBit(n)           => Self::MissingBit(n),
+            FrameError::ParityError(never)      => match never {},
+            FrameError::InvalidDigit(_, value)  => Self::InvalidDigit(field, value),
+            FrameError::InvalidValue(..) if field == Field::LeapSecond => Self::InvalidLeapSecond,
+            FrameError::InvalidValue(_, value)  => Self::OutOfRange(field, value as i16),
+        }
+    }
+
+    fn out_of_range(field: Field, value: i16) -> Self {
+        Self::OutOfRange(field, value)
+    }
+}
+
+impl fmt::Display for DecodingFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::NotEnoughBits     => write!(f, "not enough seconds for a full minute"),
+            Self::NotSync           => write!(f, "position markers out of place"),
+            Self::MissingBit(n)     => write!(f, "second {} is missing", n),
+            Self::UnusedBitSet(n)   => write!(f, "unused second {} is set", n),
+            Self::ParityError(field) => match field.parity() {
+                Some((bits, parity)) =>
+                    write!(f, "{} parity error ({}, parity bit {})", field, BitRange(bits), parity),
+                None => write!(f, "{} parity error", field),
+            },
+            Self::InvalidLeapSecond => write!(f, "invalid leap second bits (seconds 53-54)"),
+            Self::UnknownYear       => write!(f, "call sign minute, year not known yet"),
+            Self::InvalidDigit(field, v) =>
+                write!(f, "invalid {} digit {} ({})", field, v, BitRange(field.bit_range())),
+            Self::OutOfRange(field, v) =>
+                write!(f, "{} {} out of range ({})", field, v, BitRange(field.bit_range())),
+        }
+    }
+}
+
+pub type Error = DecodeError<DecodingFailure>;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JjyFrame {
+    // Start of the minute the frame was transmitted in
+    pub time: Zoned,
+    pub century: CenturyInference,
+    // Not transmitted in call sign minutes
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_util::option_weekday"))]
+    pub weekday: Option<Weekday>,
+    pub leap_second: Option<LeapSecond>,
+    pub call_sign: bool
+}
+
+// Numbers covered by PA1 or PA2 are checked against them
+fn decode_number(bits: &[Bit], field: Field, decoded: core::result::Result<i16, FrameError<Infallible>>, valid: Range<i16>) -> Result<i16> {
+    let value = decode_field(field, decoded, valid)?;
+    if let Some((range, parity_bit)) = field.parity() {
+        let ones = bits[range].iter().filter(|b| **b == Bit::Value(true)).count();
+        let parity = bits[parity_bit].to_bit()
+            .ok_or(DecodingFailure::MissingBit(parity_bit))?;
+        if ones % 2 != parity as usize {
+            return Err(From::from(DecodingFailure::ParityError(field)))
+        }
+    }
+    Ok(value)
+}
+
+// Unused seconds are always 0
+fn check_unused(bits: &[Bit], unused: impl IntoIterator<Item = usize>) -> Result<()> {
+    for n in unused {
+        match bits[n].to_bit() {
+            Some(false) => (),
+            Some(true) => return Err(From::from(DecodingFailure::UnusedBitSet(n))),
+            None => return Err(From::from(DecodingFailure::MissingBit(n)))
+        }
+    }
+    Ok(())
+}
+
+// Year of the last complete frame, for the call sign minutes
+#[derive(Clone, Copy, Debug)]
+struct KnownYear {
+    year: i16,
+    day_of_year: i16,
+    century: CenturyInference
+}
+
+pub struct Decoder {
+    sync: MarkerSync,
+    century: CenturyPolicy,
+    known_year: Option<KnownYear>
+}
+
+impl fmt::Display for Decoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.sync.fmt(f)
+    }
+}
+
+impl Decoder {
+    pub fn new() -> Decoder {
+        Decoder {
+            sync: MarkerSync::new(LAYOUT),
+            century: CenturyPolicy::default(),
+            known_year: None
+        }
+    }
+
+    pub fn with_century(mut self, century: CenturyPolicy) -> Decoder {
+        self.century = century;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.sync.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.sync.is_full()
+    }
+
+    pub fn add_symbol(&mut self, value: MarkerSymbol) -> &Self {
+        self.sync.add_symbol(value);
+        if value == MarkerSymbol::Marker
+            && let Ok(frame) = self.decode_frame()
+            && !frame.call_sign {
+            self.known_year = Some(KnownYear {
+                year: frame.time.year(),
+                day_of_year: frame.time.day_of_year(),
+                century: frame.century
+            });
+        }
+        self
+    }
+
+    pub fn add_pulse(&mut self, pulse: u8) -> &Self {
+        self.add_symbol(decode_pulse(pulse))
+    }
+
+    pub fn add_symbol_iter(&mut self, iter: impl IntoIterator<Item = MarkerSymbol>) -> &Self {
+        for symbol in iter {
+            self.add_symbol(symbol);
+        }
+        self
+    }
+
+    pub fn decode_jjy(&self) -> Result<Zoned> {
+        self.decode_frame().map(|frame| frame.time)
+    }
+
+    pub fn decode_frame(&self) -> Result<JjyFrame> {
+        if !self.sync.is_full() {
+            return Err(From::from(DecodingFailure::NotEnoughBits))
+        }
+        let frame = self.sync.frame_ignoring(CALL_SIGN_SECONDS)
+            .ok_or(DecodingFailure::NotSync)?;
+        let bits = marker_bits(&frame);
+
+        check_unused(&bits, JjyFields::UNUSED_BITS.iter().copied())?;
+        let minute = decode_number(&bits, Field::Minute, JjyFields::decode_minute(&bits), 0..60)?;
+        let hour = decode_number(&bits, Field::Hour, JjyFields::decode_hour(&bits), 0..24)?;
+        let day_of_year = decode_number(&bits, Field::DayOfYear, JjyFields::decode_day_of_year(&bits), 1..367)?;
+
+        let call_sign = minute == 15 || minute == 45;
+        let (full_year, century, weekday, leap_second) = if call_sign {
+            check_unused(&bits, UNUSED_BITS_CALL_SIGN)?;
+            let known = self.known_year
+                .ok_or(DecodingFailure::UnknownYear)?;
+            // The year may have changed since the last complete frame
+            let year = if day_of_year < known.day_of_year { known.year + 1 } else { known.year };
+            (year, known.century, None, None)
+        } else {
+            if !LAYOUT.is_aligned(&frame) {
+                return Err(From::from(DecodingFailure::NotSync))
+            }
+            check_unused(&bits, JjyFields::SPARE_BITS)?;
+            let year = decode_number(&bits, Field::Year, JjyFields::decode_year(&bits), 0..100)?;
+            let weekday_number = decode_value(Field::Weekday, JjyFields::decode_weekday(&bits))?;
+            let weekday = Weekday::from_sunday_zero_offset(weekday_number as i8).ok();
+            let leap_second = decode_value(Field::LeapSecond, JjyFields::decode_leap_second(&bits))?;
+            let (full_year, century) = self.century.full_year_of_day(year as i8, day_of_year, weekday);
+            (full_year, century, weekday, Some(leap_second))
+        };
+
+        let date = Date::new(full_year, 1, 1)?
+            .with()
+            .day_of_year(day_of_year)
+            .build()
+            .map_err(|_| DecodingFailure::OutOfRange(Field::DayOfYear, day_of_year))?;
+        let time = date.at(hour as i8, minute as i8, 0, 0)
+            .to_zoned(tz::offset(9).to_time_zone())?;
+        Ok(JjyFrame { time, century, weekday, leap_second, call_sign })
+    }
+}
+
+impl TimeCodeDecoder for Decoder {
+    fn name(&self) -> &'static str {
+        "jjy"
+    }
+
+    fn pulse_timing(&self) -> PulseTiming {
+        PULSE_TIMING
+    }
+
+    fn failure_kinds(&self) -> Vec<&'static str> {
+        time_code::failure_kinds(&DecodingFailure::KINDS)
+    }
+
+    fn pulse_thresholds(&self) -> Vec<(u32, char)> {
+        time_code::width_thresholds(&PULSE_CLASSES)
+    }
+
+    fn frame_len(&self) -> usize {
+        LAYOUT.len
+    }
+
+    fn alphabet(&self) -> &'static str {
+        "01M?_"
+    }
+
+    fn layout(&self) -> &'static [FieldSpan] {
+        JjyFields::LAYOUT
+    }
+
+    fn feed_pulse(&mut self, pulse: u8) -> char {
+        let symbol = decode_pulse(pulse);
+        self.add_symbol(symbol);
+        symbol_char(symbol)
+    }
+
+    fn feed_symbol(&mut self, symbol: char) -> bool {
+        let Some(symbol) = MarkerSymbol::from_char(symbol) else { return false };
+        self.add_symbol(symbol);
+        true
+    }
+
+    // The call sign replaces the pulses at minutes 15 and 45
+    fn gaps(&self) -> &'static [usize] {
+        &[40, 41, 42, 43, 44, 45, 46, 47, 48]
+    }
+
+    fn is_synced(&self) -> bool {
+        self.sync.frame_ignoring(CALL_SIGN_SECONDS).is_some()
+    }
+
+    fn frame_bits(&self) -> Vec<Bit> {
+        marker_bits(&self.sync.symbols())
+    }
+
+    // The frame gives the minute it was sent in
+    fn decode(&self) -> time_code::Result<DecodedFrame> {
+        let frame = self.decode_frame()?;
+        let next_frame = frame.time.checked_add(SignedDuration::from_mins(1))
+            .map_err(Error::from)?;
+        let leap_second = frame.leap_second.unwrap_or(LeapSecond::None);
+        // Japan has no summer time
+        Ok(DecodedFrame {
+            time: frame.time,
+            next_frame,
+            century: frame.century,
+            leap_second,
+            summer_time: false,
+            summer_time_change: false
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::marker_sync::marker_symbols;
+    use MarkerSymbol::*;
+
+    fn build_signal(minute: i16) -> Vec<MarkerSymbol> {
+        let fields = JjyFields {
+            minute,
+            hour: 12,
+            day_of_year: 186,
+            pa1: false,
+            // Even parity over the BCD digits, hour 12 has two ones
+            pa2: ((minute / 10).count_ones() + (minute % 10).count_ones()) & 1 == 1,
+            su1: false,
+            su2: false,
+            year: 16,
+            weekday: 1,
+            leap_second: LeapSecond::None
+        };
+        marker_symbols(&fields.encode_bits().expect("Could not encode the frame"))
+    }
+
+    // 2016-07-04 (day 186, Monday) 12:34 JST
+    fn build_valid_signal() -> Vec<MarkerSymbol> {
+        build_signal(34)
+    }
+
+    #[test]
+    fn test_decode_pulse() {
+        assert_eq!(decode_pulse(0b1), Zero);
+        assert_eq!(decode_pulse(0b1111), One);
+        assert_eq!(decode_pulse(0b111_1111), Marker);
+        assert_eq!(decode_pulse(0xFF), Marker);
+        assert_eq!(decode_pulse(0b0), Skipped);
+    }
+
+    #[test]
+    fn test_decoder_valid_signal() {
+        let mut signal = build_valid_signal();
+        let mut decoder = Decoder::new();
+        decoder.add_symbol_iter(signal.iter().copied());
+        let frame = decoder.decode_frame()
+            .expect("Could not decode jjy time");
+        assert_eq!(frame.time.to_string(), "2016-07-04T12:34:00+09:00[+09:00]");
+        assert_eq!(frame.weekday, Some(Weekday::Monday));
+        assert_eq!(frame.leap_second, Some(LeapSecond::None));
+        assert!(!frame.call_sign);
+
+        // Only 2016-07-04 of the candidate years is a Monday
+        let mut weekday = Decoder::new()
+            .with_century(CenturyPolicy::Weekday(jiff::civil::date(2026, 1, 10)));
+        weekday.add_symbol_iter(signal.iter().copied());
+        assert_eq!(weekday.decode_frame().map(|frame| frame.century).ok(), Some(CenturyInference::Unique));
+
+        signal[37] = Zero;
+        decoder.add_symbol_iter(signal.iter().copied());
+        let error = decoder.decode_jjy().unwrap_err();
+        assert!(matches!(error, Error::DecodingError(DecodingFailure::ParityError(Field::Minute))));
+        assert_eq!(error.to_string(), "minute parity error (bits 1-8, parity bit 37)");
+    }
+
+    #[test]
+    fn test_decoder_call_sign() {
+        // 12:45 with morse code in seconds 40-48
+        let mut signal = build_signal(45);
+        for (n, symbol) in [Marker, One, Unknown, Marker, Zero, Marker, Skipped, One, Marker].iter().enumerate() {
+            signal[40 + n] = *symbol;
+        }
+        signal[52] = Zero;
+        signal[50] = One;
+
+        let mut decoder = Decoder::new();
+        decoder.add_symbol_iter(signal.iter().copied());
+        assert!(matches!(decoder.decode_jjy(),
+            Err(Error::DecodingError(DecodingFailure::UnknownYear))));
+
+        decoder.add_symbol_iter(build_valid_signal());
+        decoder.add_symbol_iter(signal.iter().copied());
+        let frame = decoder.decode_frame()
+            .expect("Could not decode jjy time");
+        assert_eq!(frame.time.to_string(), "2016-07-04T12:45:00+09:00[+09:00]");
+        assert_eq!(frame.weekday, None);
+        assert!(frame.call_sign);
+    }
+}
@@ -0,0 +1,49 @@
+/* The decoders, as a library for the rust-tools binary and for other
+ * receivers. Without the (default) std feature, only the allocation-free
+ * core is built, e.g. to run the DCF77 decoder on a microcontroller.
+ */
+#![cfg_attr(not(feature = "std"), no_std)]
+// Decoders are built with new() and report how many bits they hold
+#![allow(clippy::new_without_default, clippy::len_without_is_empty)]
+
+pub mod bit_frame;
+pub mod bitrep;
+pub mod century;
+pub mod dcf77_decoder;
+pub mod frame_view;
+pub mod pulse_decoder;
+#[cfg(feature = "serde")]
+pub mod serde_util;
+
+#[cfg(feature = "std")]
+pub mod chrony_sock;
+#[cfg(all(feature = "std", feature = "serde"))]
+pub mod event_log;
+#[cfg(feature = "std")]
+pub mod gpsd_server;
+#[cfg(feature = "std")]
+pub mod irig_decoder;
+#[cfg(feature = "std")]
+pub mod jjy_decoder;
+#[cfg(feature = "std")]
+pub mod marker_sync;
+#[cfg(feature = "std")]
+pub mod meinberg;
+#[cfg(feature = "std")]
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod msf_decoder;
+#[cfg(feature = "std")]
+pub mod nmea;
+#[cfg(feature = "std")]
+pub mod ntp_shm;
+#[cfg(feature = "std")]
+pub mod raspi_refclock;
+#[cfg(feature = "std")]
+pub mod serial_output;
+#[cfg(feature = "std")]
+pub mod station_detect;
+#[cfg(feature = "std")]
+pub mod time_code;
+#[cfg(feature = "std")]
+pub mod wwvb_decoder;
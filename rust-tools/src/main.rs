@@ -1,17 +1,25 @@
 // use std::error::Error;
-use std::io;
+use std::io::{self, IsTerminal};
 use std::io::{Read,Write,Result};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
-use jiff::Zoned;
-use serialport::{self, ClearBuffer, SerialPort};
+use jiff::{SignedDuration, Timestamp, Zoned};
+use serialport::{self, ClearBuffer, DataBits, Parity, SerialPort, StopBits, TTYPort};
 
-use crate::dcf77_decoder::{DecodingFailure, Error};
-
-mod bitrep;
-mod dcf77_decoder;
-mod pulse_decoder;
-mod raspi_refclock;
+use rust_tools::century::CenturyPolicy;
+use rust_tools::frame_view::RenderStyle;
+use rust_tools::chrony_sock::ChronySock;
+use rust_tools::event_log::{Event, EventLog};
+use rust_tools::gpsd_server::GpsdServer;
+use rust_tools::metrics::{self, Metrics, MetricsServer};
+use rust_tools::meinberg::{self, MeinbergClock};
+use rust_tools::nmea;
+use rust_tools::ntp_shm::{self, ShmRefclock};
+use rust_tools::serial_output::SerialOutput;
+use rust_tools::station_detect::{CandidateDecoders, StationDetector};
+use rust_tools::time_code::{DecodedFrame, DecoderOptions, FrameLock, LeapSecond, RefclockSample, TimeCodeDecoder};
+use rust_tools::{pulse_decoder, raspi_refclock, station_detect, time_code};
 
 /*
 // FIXME: Can't get to serialport::posix::termios
@@ -21,98 +29,614 @@ fn print_termios(port: &TTYPort) {
 }
 */
 
+// Human-readable output, to stderr while stdout carries JSON events
+static TEXT_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+macro_rules! text {
+    ($($arg:tt)*) => {
+        if TEXT_TO_STDERR.load(Ordering::Relaxed) { eprint!($($arg)*) } else { print!($($arg)*) }
+    }
+}
+
+macro_rules! textln {
+    ($($arg:tt)*) => {
+        if TEXT_TO_STDERR.load(Ordering::Relaxed) { eprintln!($($arg)*) } else { println!($($arg)*) }
+    }
+}
+
+// Time servers fed with our second and minute marks
+struct Refclocks {
+    shm: Vec<ShmRefclock>,
+    chrony: Option<ChronySock>,
+    gpsd: Option<GpsdServer>,
+    nmea: Option<SerialOutput>,
+    meinberg: Option<(SerialOutput, MeinbergClock)>
+}
+
+impl Refclocks {
+    fn poll(&mut self) {
+        if let Some(ref mut gpsd) = self.gpsd {
+            gpsd.poll();
+        }
+    }
+
+    // At the start of every second, with its time if locked and the last decoded frame
+    fn second(&mut self, time: Option<&Zoned>, frame: Option<&DecodedFrame>, started: Timestamp) {
+        // First, the parse driver takes the time from the arrival of its STX
+        if let Some((ref mut output, ref mut clock)) = self.meinberg {
+            let telegram = clock.second(time, frame, started);
+            if let Err(e) = output.write_second(telegram.as_bytes()) {
+                textln!("meinberg: {}: {}", output.path(), e);
+            }
+        }
+        if let Some(ref mut nmea) = self.nmea {
+            let mut sentences = time.map(nmea::zda).unwrap_or_default();
+            sentences.push_str(&nmea::rmc(time));
+            if let Err(e) = nmea.write_second(sentences.as_bytes()) {
+                textln!("nmea: {}: {}", nmea.path(), e);
+            }
+        }
+        if let Some(ref mut gpsd) = self.gpsd {
+            gpsd.report_time(time);
+        }
+    }
+
+    fn publish(&mut self, sample: &RefclockSample, minute_mark: bool) {
+        if minute_mark {
+            for refclock in &mut self.shm {
+                refclock.publish(sample);
+                textln!("ntp shm {}: {} received at {}", refclock.unit(), sample.clock, sample.receive);
+            }
+            if let Some(ref mut gpsd) = self.gpsd {
+                gpsd.report_offset(sample);
+            }
+        }
+        if let Some(ref chrony) = self.chrony && (minute_mark || chrony.every_second()) {
+            match chrony.send(sample) {
+                Ok(()) => textln!("chrony: {} offset {:#}", sample.clock, sample.offset()),
+                Err(e) => textln!("chrony: {}: {}", chrony.path().display(), e)
+            }
+        }
+    }
+}
+
 struct DebuggingDecoder {
-    dcf_decoder: dcf77_decoder::Decoder,
-    last_decoded: Option<Zoned>
+    decoder: Box<dyn TimeCodeDecoder>,
+    last_decoded: Option<Zoned>,
+    // For its announcements
+    last_frame: Option<DecodedFrame>,
+    // Reported once until decoding succeeds or loses sync
+    last_failure: Option<String>,
+    // From decoding a frame during the current read
+    current_error: Option<time_code::Error>,
+    // Fast codes (IRIG-B) are shown at most once per second
+    last_render: Option<Timestamp>,
+    lock: FrameLock,
+    // System time the last pulse started at, missing ones included
+    last_pulse: Timestamp,
+    // When the next second starts, predicted from the last pulse at a second, and its time while locked
+    next_second: Timestamp,
+    next_second_time: Option<Zoned>,
+    events: Option<EventLog>,
+    metrics: Metrics,
+    refclocks: Refclocks
 }
 
 impl DebuggingDecoder {
-    fn new() -> DebuggingDecoder {
+    fn new(decoder: Box<dyn TimeCodeDecoder>, events: Option<EventLog>, refclocks: Refclocks) -> DebuggingDecoder {
+        let metrics = Metrics::new(decoder.pulse_thresholds(), &decoder.failure_kinds());
+        let lock = FrameLock::new(decoder.as_ref());
+        let now = Timestamp::now();
         DebuggingDecoder {
-            dcf_decoder: dcf77_decoder::Decoder::new(),
-            last_decoded: None
+            decoder,
+            last_decoded: None,
+            last_frame: None,
+            last_failure: None,
+            current_error: None,
+            last_render: None,
+            lock,
+            last_pulse: now,
+            next_second: now + SignedDuration::from_secs(1),
+            next_second_time: None,
+            events,
+            metrics,
+            refclocks
+        }
+    }
+
+    fn log_event(&mut self, timestamp: Timestamp, event: &Event) {
+        if let Some(ref mut events) = self.events && let Err(e) = events.write(timestamp, event) {
+            eprintln!("events: {}", e);
+        }
+    }
+
+    // Once the buffer holds a whole frame, with the system time its last pulse started at
+    fn decode_frame(&mut self, started: Timestamp) -> Option<DecodedFrame> {
+        match self.decoder.decode() {
+            Ok(frame) => {
+                if frame.century.is_ambiguous() {
+                    textln!("    century: ambiguous ({:?})", frame.century);
+                }
+                if self.last_frame.as_ref() != Some(&frame) {
+                    self.log_event(started, &Event::Frame { frame: &frame });
+                    self.metrics.frame(started);
+                }
+                self.last_failure = None;
+                self.last_decoded = Some(frame.time.clone());
+                self.last_frame = Some(frame.clone());
+                Some(frame)
+            },
+            Err(e) => {
+                let reason = self.decoder.report(&e);
+                if self.last_failure.as_ref() != Some(&reason) {
+                    let kind = e.kind();
+                    self.log_event(started, &Event::Failure { kind, reason: &reason });
+                    self.metrics.failure(kind);
+                    self.last_failure = Some(reason);
+                }
+                self.current_error = Some(e);
+                None
+            }
         }
     }
 
-    fn handle_signal_byte(&mut self, signal: u8) {
+    // `started`: system time the pulse started at
+    fn handle_signal_byte(&mut self, signal: u8, started: Timestamp) -> char {
         // io::stdout().write_all(values).unwrap();
-        let bit = pulse_decoder::decode_pulse(signal ^ 0xFF);
-        println!("Signal {:08b} = {}", signal, bit);
-        self.dcf_decoder.add_bit(bit);
+        let pulse = self.decoder.pulse_timing().measure(signal ^ 0xFF);
+        let symbol = self.decoder.feed_pulse(signal ^ 0xFF);
+        let decoded = if self.decoder.is_synced() {
+            self.decode_frame(started)
+        } else {
+            self.last_failure = None;
+            None
+        };
+        let was_locked = self.lock.is_locked();
+        let second = self.lock.update(symbol, decoded.as_ref());
+        self.log_event(started, &Event::Pulse { byte: signal, pulse });
+        self.log_event(started, &Event::Bit { symbol, second: second.as_ref() });
+        self.metrics.pulse(symbol);
+        if was_locked != self.lock.is_locked() {
+            self.metrics.set_locked(self.lock.is_locked());
+            self.log_event(started, &Event::Sync { locked: self.lock.is_locked() });
+        }
+        self.last_pulse = started;
+        // Several pulses per second (IRIG-B) are summed up per read
+        let slow = self.decoder.pulse_timing().period_ms >= 1000;
+        match second {
+            Some(ref time) if time.subsec_nanosecond() == 0 => {
+                self.next_second = started + SignedDuration::from_secs(1);
+                self.next_second_time = time.checked_add(SignedDuration::from_secs(1)).ok();
+            }
+            None if slow && signal != 0xFF => {
+                self.next_second = started + SignedDuration::from_secs(1);
+                self.next_second_time = None;
+            }
+            _ => ()
+        }
+        match second {
+            Some(time) => {
+                if slow {
+                    textln!("Signal {:08b} = {}  @ {}", signal, symbol, time);
+                }
+                // Locked by a decoded frame, so this pulse marks a confirmed second
+                if signal != 0xFF && time.subsec_nanosecond() == 0 {
+                    let leap_second = self.last_frame.as_ref().map_or(LeapSecond::None, |frame| frame.leap_second);
+                    let sample = RefclockSample { clock: time.timestamp(), receive: started, leap_second };
+                    self.metrics.set_offset(sample.offset());
+                    self.refclocks.publish(&sample, time.second() == 0);
+                }
+            }
+            None if slow => textln!("Signal {:08b} = {}", signal, symbol),
+            None => ()
+        }
+        symbol
     }
 
-    fn handle_signal_bytes(&mut self, signal: &[u8]) {
-        for _ in 0..10 {
-            println!();
+    // The frame so far, the last error and time
+    fn print_status(&self) {
+        textln!();
+        let style = if io::stdout().is_terminal() { RenderStyle::Ansi } else { RenderStyle::Plain };
+        text!("{}", self.decoder.render(style));
+
+        match self.current_error {
+            Some(ref e) => {
+                textln!("    last error: {}", self.decoder.report(e));
+                if let Some(partial) = self.decoder.partial() {
+                    textln!("    partial:    {}", partial);
+                }
+            }
+            None => textln!("    last error: -")
         }
-        for value in signal {
-            self.handle_signal_byte(*value);
+
+        let name = self.decoder.name();
+        match self.last_decoded {
+            Some(ref time) => textln!("{}:  {}", name, time),
+            None => textln!("{}: <no signal>", name)
         }
 
-        println!();
-        println!("{:>60}", dcf77_decoder::DECODE_HEADER);
-        println!("{:>60}", self.dcf_decoder);
+        match self.lock.last() {
+            Some(time) => textln!("second: {}", time),
+            None => textln!("second: <not locked>")
+        }
+    }
 
-        let mut current_error: Option<Error> = None;
-        match self.dcf_decoder.decode_dcf77() {
-            Ok(decoded) => self.last_decoded = Some(decoded),
-            Err(Error::DecodingError(DecodingFailure::NotSync)) => (),  /* ignore */
-            Err(e) => current_error = Some(e)
+    /* Outputs are sent on time at the start of every second, counted on
+        from the last pulse at a second (while locked, any pulse for the once
+        per second codes). Reading that pulse takes too long to wait for it.
+     */
+    fn second(&mut self, now: Timestamp) {
+        if now < self.next_second {
+            return;
         }
+        // Only the last of the seconds missed while busy
+        while self.next_second + SignedDuration::from_secs(1) <= now {
+            self.advance_second();
+        }
+        let time = self.next_second_time.clone().filter(|_| self.lock.is_locked());
+        self.refclocks.second(time.as_ref(), self.last_frame.as_ref(), self.next_second);
+        self.advance_second();
+    }
+
+    fn advance_second(&mut self) {
+        self.next_second += SignedDuration::from_secs(1);
+        self.next_second_time = self.next_second_time.take()
+            .and_then(|time| time.checked_add(SignedDuration::from_secs(1)).ok());
+    }
+
+    // Until the next second starts, or the next pulse is overdue
+    fn read_timeout(&self, now: Timestamp) -> Duration {
+        let deadline = self.next_second.min(self.pulse_deadline());
+        Duration::try_from(deadline.duration_since(now)).unwrap_or_default().max(Duration::from_millis(1))
+    }
+
+    // Half a period late, but not before PULSE_TIMEOUT, so the fast codes' missing pulses are summed up
+    fn pulse_deadline(&self) -> Timestamp {
+        let period = SignedDuration::from_millis(i64::from(self.decoder.pulse_timing().period_ms));
+        self.last_pulse + (period * 3 / 2).max(SignedDuration::try_from(PULSE_TIMEOUT).unwrap_or_default())
+    }
 
-        match current_error {
-            Some(ref e) => println!("    last error: {}", e),
-            None => println!("    last error: -")
+    // `received`: system time the read returned (right after the last byte), nothing if it timed out
+    fn handle_read(&mut self, signal: &[u8], received: Timestamp) {
+        self.second(received);
+        let timing = self.decoder.pulse_timing();
+        let period = SignedDuration::from_millis(i64::from(timing.period_ms));
+        if !signal.is_empty() {
+            // Each byte takes a while to arrive after the pulse started
+            self.handle_signal_bytes(signal, received - SignedDuration::from_millis(i64::from(timing.byte_ms())));
+        } else if received >= self.pulse_deadline() {
+            let elapsed = received.duration_since(self.last_pulse).as_millis() / i128::from(timing.period_ms);
+            let last = self.last_pulse + period * elapsed as i32;
+            // No need for more than a frame of them
+            let missing = elapsed.clamp(1, self.decoder.frame_len() as i128) as usize;
+            self.handle_signal_bytes(&vec![0xFF; missing], last);
         }
+    }
 
-        match self.last_decoded {
-            Some(ref time) => println!("dcf77:  {}", time),
-            None => println!("dcf77: <no signal>")
+    // `last`: system time the last pulse started at, one pulse per period before it
+    fn handle_signal_bytes(&mut self, signal: &[u8], last: Timestamp) {
+        let timing = self.decoder.pulse_timing();
+        let period = SignedDuration::from_millis(i64::from(timing.period_ms));
+        self.current_error = None;
+        let mut symbols = String::new();
+        for (n, value) in signal.iter().enumerate() {
+            let started = last - period * (signal.len() - 1 - n) as i32;
+            symbols.push(self.handle_signal_byte(*value, started));
+        }
+        let slow = timing.period_ms >= 1000;
+        if !slow {
+            match self.lock.last() {
+                Some(time) => textln!("Signals {}  @ {}", symbols, time),
+                None => textln!("Signals {}", symbols)
+            }
+        }
+        let due = self.last_render.is_none_or(|render| last.duration_since(render) >= SignedDuration::from_secs(1));
+        if slow || due || self.current_error.is_some() {
+            self.last_render = Some(last);
+            self.print_status();
         }
 
         io::stdout().flush().unwrap();
+        if let Some(ref mut events) = self.events && let Err(e) = events.flush() {
+            eprintln!("events: {}", e);
+        }
     }
 }
 
-fn run() -> Result<()> {
-    let mut port = raspi_refclock::setup_serial();
+struct Options {
+    format: String,
+    port: String,
+    list_ports: bool,
+    // A recorded frame (symbols of --format) to decode instead of reading the port
+    bits: Option<String>,
+    decoder: DecoderOptions,
+    // NTP SHM units to publish minute marks to
+    ntp_shm: Vec<u8>,
+    ntp_precision: i32,
+    // chrony SOCK refclock, sent minute marks or every second
+    chrony_sock: Option<String>,
+    chrony_every_second: bool,
+    // gpsd JSON protocol on localhost
+    gpsd_port: Option<u16>,
+    // NMEA sentences to a new pty ("pty") or a tty
+    nmea: Option<String>,
+    nmea_baud: u32,
+    // Meinberg standard telegram to a new pty or a tty, and a fixed link to it
+    meinberg: Option<String>,
+    meinberg_link: Option<String>,
+    // JSON Lines events to stdout ("-") or appended to a file
+    json_events: Option<String>,
+    // Prometheus metrics over HTTP
+    metrics_listen: Option<String>
+}
+
+const USAGE: &str = "usage: rust-tools [--format auto|dcf77|msf|wwvb|jjy|irig-b] [--port /dev/ttyAMA0] [--list-ports] [--bits 0110..._|M000...] [--century pivot:YYYY|weekday[:YYYY-MM-DD]] [--year YYYY] [--ntp-shm UNIT]... [--ntp-precision -6] [--chrony-sock PATH] [--chrony-every-second] [--gpsd-port 2947] [--nmea pty|TTY] [--nmea-baud 4800] [--meinberg pty|TTY] [--meinberg-link /dev/refclock-0] [--json-events -|FILE] [--metrics-listen ADDRESS[:9477]]";
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> std::result::Result<Options, String> {
+    let mut options = Options {
+        format: String::from("dcf77"),
+        port: String::from(raspi_refclock::DEFAULT_PORT),
+        list_ports: false,
+        bits: None,
+        decoder: DecoderOptions::default(),
+        ntp_shm: Vec::new(),
+        ntp_precision: ntp_shm::DEFAULT_PRECISION,
+        chrony_sock: None,
+        chrony_every_second: false,
+        gpsd_port: None,
+        nmea: None,
+        nmea_baud: nmea::DEFAULT_BAUD_RATE,
+        meinberg: None,
+        meinberg_link: None,
+        json_events: None,
+        metrics_listen: None
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => options.format = args.next().ok_or("--format needs a value")?,
+            "--port" => options.port = args.next().ok_or("--port needs a value")?,
+            "--list-ports" => options.list_ports = true,
+            "--bits" => options.bits = Some(args.next().ok_or("--bits needs a value")?),
+            "--century" => options.decoder.century = parse_century(args.next())?,
+            "--year" => options.decoder.year = Some(parse_number(args.next(), "--year")?),
+            "--ntp-shm" => options.ntp_shm.push(parse_number(args.next(), "--ntp-shm")?),
+            "--ntp-precision" => options.ntp_precision = parse_number(args.next(), "--ntp-precision")?,
+            "--chrony-sock" => options.chrony_sock = Some(args.next().ok_or("--chrony-sock needs a value")?),
+            "--chrony-every-second" => options.chrony_every_second = true,
+            "--gpsd-port" => options.gpsd_port = Some(parse_number(args.next(), "--gpsd-port")?),
+            "--nmea" => options.nmea = Some(args.next().ok_or("--nmea needs a value")?),
+            "--nmea-baud" => options.nmea_baud = parse_number(args.next(), "--nmea-baud")?,
+            "--meinberg" => options.meinberg = Some(args.next().ok_or("--meinberg needs a value")?),
+            "--meinberg-link" => options.meinberg_link = Some(args.next().ok_or("--meinberg-link needs a value")?),
+            "--json-events" => options.json_events = Some(args.next().ok_or("--json-events needs a value")?),
+            "--metrics-listen" => options.metrics_listen = Some(args.next().ok_or("--metrics-listen needs a value")?),
+            _ => return Err(format!("unknown argument: {}", arg))
+        }
+    }
+    Ok(options)
+}
+
+fn parse_number<T: std::str::FromStr>(value: Option<String>, name: &str) -> std::result::Result<T, String> {
+    let value = value.ok_or_else(|| format!("{} needs a value", name))?;
+    value.parse().map_err(|_| format!("{}: invalid number {}", name, value))
+}
+
+/* pivot:YYYY, or weekday with a reference date (today by default) the
+    decoded date should be close to
+ */
+fn parse_century(value: Option<String>) -> std::result::Result<CenturyPolicy, String> {
+    let value = value.ok_or("--century needs a value")?;
+    match value.split_once(':') {
+        Some(("pivot", base)) => base.parse().map(CenturyPolicy::Pivot)
+            .map_err(|_| format!("--century: invalid pivot year {}", base)),
+        Some(("weekday", reference)) => reference.parse().map(CenturyPolicy::Weekday)
+            .map_err(|_| format!("--century: invalid reference date {}", reference)),
+        None if value == "weekday" => Ok(CenturyPolicy::Weekday(Zoned::now().date())),
+        _ => Err(format!("--century: expected pivot:YYYY or weekday, got {}", value))
+    }
+}
+
+fn decode_bits(bits: &str, format: &str, options: &DecoderOptions) -> std::result::Result<(), String> {
+    let mut decoder = time_code::decoder_by_name(format, options)
+        .ok_or_else(|| format!("--bits needs a --format, one of {:?}", time_code::FORMAT_NAMES))?;
+    let symbols = bits.chars().enumerate().filter(|(_, c)| !(c.is_whitespace() || "|,.".contains(*c)));
+    for (position, c) in symbols {
+        if !decoder.feed_symbol(c) {
+            return Err(format!("invalid {} symbol '{}' at position {}, expected one of {}",
+                decoder.name(), c, position, decoder.alphabet()));
+        }
+    }
+    let style = if io::stdout().is_terminal() { RenderStyle::Ansi } else { RenderStyle::Plain };
+    text!("{}", decoder.render(style));
+    match decoder.decode() {
+        Ok(frame) => textln!("{}:  {}", decoder.name(), frame.time),
+        Err(e) => {
+            textln!("    error:   {}", decoder.report(&e));
+            if let Some(partial) = decoder.partial() {
+                textln!("    partial: {}", partial);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Without a pulse for this long, it is reported missing
+const PULSE_TIMEOUT: Duration = Duration::from_millis(1500);
+
+// Nothing if the timeout passes first
+fn read_pulses<'a>(port: &mut TTYPort, serial_buf: &'a mut [u8], timeout: Duration) -> Result<&'a [u8]> {
+    port.set_timeout(timeout)?;
+    match port.read(serial_buf) {
+        Ok(t) => {
+            textln!("Read {} bytes", t);
+            Ok(&serial_buf[..t])
+        }
+        Err(ref e) if e.kind() == io::ErrorKind::TimedOut => Ok(&[]),
+        Err(e) => Err(e),
+    }
+}
+
+// Missing pulses (timeouts) are reported as a single 0xFF signal byte
+fn read_signal<'a>(port: &mut TTYPort, serial_buf: &'a mut [u8]) -> Result<&'a [u8]> {
+    match read_pulses(port, serial_buf, PULSE_TIMEOUT)? {
+        [] => {
+            textln!("Read timed out");
+            Ok(&[0xFF])
+        }
+        signal => Ok(signal)
+    }
+}
+
+// Watch the pulses until they match one of the stations
+fn detect_station(port: &mut TTYPort, serial_buf: &mut [u8], options: &DecoderOptions) -> Result<Box<dyn TimeCodeDecoder>> {
+    port.set_baud_rate(1000 / station_detect::DETECT_TIMING.bit_ms)?;
+    port.clear(ClearBuffer::Input)?;
+    textln!("Detecting station, this takes at least two minutes");
+    let mut detector = StationDetector::new();
+    loop {
+        for value in read_signal(port, serial_buf)? {
+            let class = detector.add_pulse(*value ^ 0xFF);
+            textln!("Signal {:08b} = {:?}  ({} seconds)", value, class, detector.len());
+        }
+        if let Some(detection) = detector.detect() {
+            textln!("Detected {}", detection);
+            return match detection.names {
+                [name] => Ok(time_code::decoder_by_name(name, options)
+                    .expect("Detected an unknown format")),
+                names => decode_candidates(port, serial_buf, names, options)
+            }
+        }
+    }
+}
+
+// Stations that look the same until one of them decodes
+fn decode_candidates(port: &mut TTYPort, serial_buf: &mut [u8], names: &[&str], options: &DecoderOptions) -> Result<Box<dyn TimeCodeDecoder>> {
+    let mut candidates = CandidateDecoders::new(names, options)
+        .expect("Candidate stations should share the pulse timing");
+    port.set_baud_rate(1000 / candidates.pulse_timing().bit_ms)?;
+    port.clear(ClearBuffer::Input)?;
+    textln!("Decoding as {} until only one of them fits", names.join(" or "));
+    loop {
+        for value in read_signal(port, serial_buf)? {
+            if let Some(decoder) = candidates.add_pulse(*value ^ 0xFF) {
+                textln!("Decoded {}", decoder.name());
+                return Ok(decoder)
+            }
+        }
+    }
+}
+
+fn run(options: &Options) -> Result<()> {
+    let events = match options.json_events.as_deref() {
+        Some("-") => {
+            TEXT_TO_STDERR.store(true, Ordering::Relaxed);
+            Some(EventLog::stdout())
+        }
+        Some(path) => Some(EventLog::create(path)?),
+        None => None
+    };
+    let shm = options.ntp_shm.iter()
+        .map(|unit| ShmRefclock::attach(*unit, options.ntp_precision)
+            .map_err(|e| io::Error::new(e.kind(), format!("ntp shm unit {}: {}", unit, e))))
+        .collect::<Result<Vec<_>>>()?;
+    let chrony = options.chrony_sock.as_ref()
+        .map(|path| ChronySock::new(path, options.chrony_every_second))
+        .transpose()?;
+    let nmea = match options.nmea.as_deref() {
+        Some("pty") => Some(SerialOutput::pty()?),
+        Some(path) => Some(SerialOutput::open(serialport::new(path, options.nmea_baud))?),
+        None => None
+    };
+    if let Some(ref nmea) = nmea {
+        textln!("Sending NMEA sentences to {}", nmea.path());
+    }
+    let mut meinberg = match options.meinberg.as_deref() {
+        Some("pty") => Some(SerialOutput::pty()?),
+        // The receivers' default framing
+        Some(path) => Some(SerialOutput::open(serialport::new(path, meinberg::DEFAULT_BAUD_RATE)
+            .data_bits(DataBits::Seven)
+            .parity(Parity::Even)
+            .stop_bits(StopBits::Two))?),
+        None => None
+    };
+    if let Some(ref mut meinberg) = meinberg {
+        if let Some(ref link) = options.meinberg_link {
+            meinberg.link_to(link)
+                .map_err(|e| io::Error::new(e.kind(), format!("meinberg link {}: {}", link, e)))?;
+        }
+        textln!("Sending Meinberg telegrams to {}", options.meinberg_link.as_deref().unwrap_or(meinberg.path()));
+    }
+    let meinberg = meinberg.map(|output| (output, MeinbergClock::new()));
+    let mut metrics = options.metrics_listen.as_ref()
+        .map(|address| if address.contains(':') { address.clone() } else { format!("{}:{}", address, metrics::DEFAULT_PORT) })
+        .map(|address| MetricsServer::bind(address.as_str())
+            .map_err(|e| io::Error::new(e.kind(), format!("metrics {}: {}", address, e))))
+        .transpose()?;
+    if let Some(ref metrics) = metrics {
+        textln!("Serving metrics on http://{}/metrics", metrics.local_addr()?);
+    }
+    let mut port = raspi_refclock::setup_serial(&options.port, 1000 / pulse_decoder::BAUD_50.bit_ms);
+    let mut serial_buf: Vec<u8> = vec![0; 1000];
+
+    let decoder = if options.format == "auto" {
+        detect_station(&mut port, &mut serial_buf, &options.decoder)?
+    } else {
+        time_code::decoder_by_name(&options.format, &options.decoder)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput,
+                format!("unknown format {}, expected auto or one of {:?}", options.format, time_code::FORMAT_NAMES)))?
+    };
+    port.set_baud_rate(1000 / decoder.pulse_timing().bit_ms)?;
     port.clear(ClearBuffer::Input)
         .expect("Failed to discard input buffer");
 
     // thread::sleep(Duration::from_millis(100));
 
-    let mut serial_buf: Vec<u8> = vec![0; 1000];
-    println!(
+    textln!(
         "Receiving data on {} at {} baud:",
         port.name().unwrap_or(String::from("?")),
         port.baud_rate().map(|v| v.to_string()).unwrap_or(String::from("?"))
     );
+    textln!("Decoding {} (symbols {})", decoder.name(), decoder.alphabet());
 
-    let mut decoder: DebuggingDecoder = DebuggingDecoder::new();
+    let gpsd = options.gpsd_port
+        .map(|port| GpsdServer::bind(("127.0.0.1", port), &options.port, decoder.name(), options.ntp_precision))
+        .transpose()?;
+    let refclocks = Refclocks { shm, chrony, gpsd, nmea, meinberg };
+    let mut decoder: DebuggingDecoder = DebuggingDecoder::new(decoder, events, refclocks);
 
     loop {
-        port.set_timeout(Duration::from_millis(1500))?;
-        match port.read(serial_buf.as_mut_slice()) {
-            Ok(t) => {
-                println!("Read {} bytes", t);
-                decoder.handle_signal_bytes(&serial_buf[..t]);
-            }
-            Err(ref e) if e.kind() == io::ErrorKind::TimedOut => {
-                println!("Read timed out");
-                decoder.handle_signal_bytes(&[0xFF]);
-            },
-            Err(e) => return Err(e),
+        let timeout = decoder.read_timeout(Timestamp::now());
+        let signal = read_pulses(&mut port, &mut serial_buf, timeout)?;
+        decoder.handle_read(signal, Timestamp::now());
+        decoder.refclocks.poll();
+        if let Some(ref mut metrics) = metrics {
+            metrics.poll(&decoder.metrics);
         }
     }
 }
 
 fn main() {
-    // TODO: Command line parsing
-    // see e.g. https://github.com/serialport/serialport-rs/blob/main/examples/clear_input_buffer.rs
-
-    // list_serial_ports();
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            println!("Error: {}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.list_ports {
+        raspi_refclock::list_serial_ports();
+        return;
+    }
+    if let Some(ref bits) = options.bits {
+        if let Err(e) = decode_bits(bits, &options.format, &options.decoder) {
+            println!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    let exit_code = match run() {
+    let exit_code = match run(&options) {
         Ok(_) => 0,
         Err(e) => {
             println!("Error: {}", e);
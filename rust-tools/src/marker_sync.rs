@@ -0,0 +1,201 @@
+use std::fmt::{self};
+use std::ops::Range;
+
+use ringbuffer::{RingBuffer,AllocRingBuffer};
+
+use crate::bitrep::*;
+
+/* Frame sync for marker based time codes (WWVB, JJY, IRIG-B):
+ * every second (or bit) is a zero, a one or a position marker. Markers sit
+ * at fixed positions, and the frame reference marker directly follows the
+ * last marker of the previous frame, so two markers in a row mark the start
+ * of a frame.
+ */
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarkerSymbol {
+    Zero,
+    One,
+    Marker,
+    Unknown,
+    Skipped
+}
+
+impl MarkerSymbol {
+    pub fn bit(&self) -> Bit {
+        match *self {
+            Self::Zero      => Bit::Value(false),
+            Self::One       => Bit::Value(true),
+            Self::Skipped   => Bit::Skipped,
+            _               => Bit::Unknown,
+        }
+    }
+
+    // Inverse of `Display`
+    pub fn from_char(c: char) -> Option<MarkerSymbol> {
+        match c {
+            '0' => Some(Self::Zero),
+            '1' => Some(Self::One),
+            'M' => Some(Self::Marker),
+            '?' => Some(Self::Unknown),
+            '_' => Some(Self::Skipped),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for MarkerSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match *self {
+            Self::Zero      => "0",
+            Self::One       => "1",
+            Self::Marker    => "M",
+            Self::Unknown   => "?",
+            Self::Skipped   => "_",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MarkerLayout {
+    pub len: usize,
+    // Positions of the markers, starting with the frame reference at 0
+    pub markers: &'static [usize]
+}
+
+impl MarkerLayout {
+    /* A frame is aligned if it starts and ends with a marker, no data
+        position holds a marker, and the other markers are either present
+        or unreadable.
+     */
+    pub fn is_aligned(&self, frame: &[MarkerSymbol]) -> bool {
+        self.is_aligned_ignoring(frame, 0..0)
+    }
+
+    // Same, but any symbol goes in the `ignored` positions (e.g. JJY call sign)
+    pub fn is_aligned_ignoring(&self, frame: &[MarkerSymbol], ignored: Range<usize>) -> bool {
+        if frame.len() != self.len
+            || frame.first() != Some(&MarkerSymbol::Marker)
+            || frame.last() != Some(&MarkerSymbol::Marker) {
+            return false
+        }
+        frame.iter().enumerate().all(|(n, symbol)| {
+            ignored.contains(&n) || match (self.markers.contains(&n), *symbol) {
+                (true, MarkerSymbol::Marker | MarkerSymbol::Unknown) => true,
+                (true, _) => false,
+                (false, MarkerSymbol::Marker) => false,
+                (false, _) => true,
+            }
+        })
+    }
+}
+
+// The bit carried by each symbol, markers as skipped (e.g. for the frame view)
+pub fn marker_bits(symbols: &[MarkerSymbol]) -> Vec<Bit> {
+    symbols.iter()
+        .map(|s| if *s == MarkerSymbol::Marker { Bit::Skipped } else { s.bit() })
+        .collect()
+}
+
+// Inverse of `marker_bits`, for frames encoded by their `BitFrame`: bits left unknown are zeros
+#[cfg(test)]
+pub fn marker_symbols(bits: &[Bit]) -> Vec<MarkerSymbol> {
+    bits.iter()
+        .map(|bit| match *bit {
+            Bit::Skipped        => MarkerSymbol::Marker,
+            Bit::Value(true)    => MarkerSymbol::One,
+            _                   => MarkerSymbol::Zero,
+        })
+        .collect()
+}
+
+pub struct MarkerSync {
+    layout: MarkerLayout,
+    buffer: AllocRingBuffer<MarkerSymbol>
+}
+
+impl fmt::Display for MarkerSync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&self.buffer.iter().map(|x| x.to_string()).collect::<String>())
+    }
+}
+
+impl MarkerSync {
+    pub fn new(layout: MarkerLayout) -> MarkerSync {
+        MarkerSync {
+            layout,
+            buffer: AllocRingBuffer::new(layout.len)
+        }
+    }
+
+    pub fn layout(&self) -> MarkerLayout {
+        self.layout
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.buffer.is_full()
+    }
+
+    pub fn add_symbol(&mut self, value: MarkerSymbol) -> &Self {
+        self.buffer.enqueue(value);
+        self
+    }
+
+    pub fn symbols(&self) -> Vec<MarkerSymbol> {
+        self.buffer.to_vec()
+    }
+
+    pub fn is_aligned(&self) -> bool {
+        self.buffer.is_full() && self.layout.is_aligned(&self.buffer.to_vec())
+    }
+
+    // The current frame, if the buffer holds exactly one aligned frame
+    pub fn frame(&self) -> Option<Vec<MarkerSymbol>> {
+        self.frame_ignoring(0..0)
+    }
+
+    pub fn frame_ignoring(&self, ignored: Range<usize>) -> Option<Vec<MarkerSymbol>> {
+        let frame = self.buffer.to_vec();
+        if self.buffer.is_full() && self.layout.is_aligned_ignoring(&frame, ignored) {
+            Some(frame)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAYOUT: MarkerLayout = MarkerLayout { len: 10, markers: &[0, 4, 9] };
+
+    #[test]
+    fn test_marker_sync() {
+        use MarkerSymbol::*;
+        let mut sync = MarkerSync::new(LAYOUT);
+        for symbol in [Marker, One, Zero, One, Marker, Zero, Zero, One, One] {
+            sync.add_symbol(symbol);
+        }
+        assert_eq!(sync.frame(), None);
+        sync.add_symbol(Marker);
+        assert_eq!(sync.frame().map(|f| marker_bits(&f[..4])),
+            Some(vec![Bit::Skipped, Bit::Value(true), Bit::Value(false), Bit::Value(true)]));
+
+        // Unreadable markers are fine, misplaced ones are not
+        sync.add_symbol(Marker);
+        assert!(!sync.is_aligned());
+        for symbol in [One, Zero, One, Unknown, Zero, Zero, One, One, Marker] {
+            sync.add_symbol(symbol);
+        }
+        assert!(sync.is_aligned());
+        for symbol in [Marker, One, Zero, One, Zero, Marker, Zero, One, One, Marker] {
+            sync.add_symbol(symbol);
+        }
+        assert!(!sync.is_aligned());
+    }
+}
@@ -0,0 +1,123 @@
+use jiff::{SignedDuration, Timestamp, Zoned, tz::{Offset, TimeZone}};
+
+use crate::time_code::{DecodedFrame, LeapSecond};
+
+/* The Meinberg standard time telegram, as sent by their DCF77 receivers
+ * once per second and read by ntpd's parse driver (127.127.8.x mode 0):
+ *
+ *      <STX>D:dd.mm.yy;T:w;U:hh.mm.ss;uvxy<ETX>
+ *
+ *      w   day of the week, 1 = Monday
+ *      u   '#' not synchronized since start, ' ' otherwise
+ *      v   '*' free running, ' ' while locked
+ *      x   'U' UTC, ' ' CET, 'S' CEST
+ *      y   '!' summer time change announced, 'A' leap second announced
+ *
+ * The receiver only knows CET and CEST, so other stations' times are sent
+ * as UTC. While not locked we keep counting seconds from the last locked
+ * one on the system clock, flagged free running.
+ */
+
+pub const DEFAULT_BAUD_RATE: u32 = 9600;
+
+const STX: char = '\x02';
+const ETX: char = '\x03';
+
+const CET: Offset = Offset::constant(1);
+const CEST: Offset = Offset::constant(2);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Status {
+    pub never_synced: bool,
+    pub free_running: bool,
+    pub summer_time_change: bool,
+    pub leap_second: bool
+}
+
+pub fn telegram(time: &Zoned, status: Status) -> String {
+    let (time, zone) = match time.offset() {
+        offset if offset == CET => (time.clone(), ' '),
+        offset if offset == CEST => (time.clone(), 'S'),
+        _ => (time.with_time_zone(TimeZone::UTC), 'U')
+    };
+    let announcement = if status.leap_second {
+        'A'
+    } else if status.summer_time_change {
+        '!'
+    } else {
+        ' '
+    };
+    format!("{}D:{};T:{};U:{};{}{}{}{}{}",
+        STX,
+        time.strftime("%d.%m.%y"),
+        time.weekday().to_monday_one_offset(),
+        time.strftime("%H.%M.%S"),
+        if status.never_synced { '#' } else { ' ' },
+        if status.free_running { '*' } else { ' ' },
+        zone,
+        announcement,
+        ETX)
+}
+
+pub struct MeinbergClock {
+    // The last locked second and the system time it started at
+    last_locked: Option<(Zoned, Timestamp)>
+}
+
+impl MeinbergClock {
+    pub fn new() -> MeinbergClock {
+        MeinbergClock { last_locked: None }
+    }
+
+    /* When the second starts (the system time `started`), with its time if
+        locked and the last decoded frame. The receivers send the STX right
+        at the start, that's where the parse driver takes the time from.
+     */
+    pub fn second(&mut self, time: Option<&Zoned>, frame: Option<&DecodedFrame>, started: Timestamp) -> String {
+        if let Some(time) = time {
+            self.last_locked = Some((time.clone(), started));
+            let status = Status {
+                summer_time_change: frame.is_some_and(|frame| frame.summer_time_change),
+                leap_second: frame.is_some_and(|frame| frame.leap_second != LeapSecond::None),
+                ..Status::default()
+            };
+            return telegram(time, status);
+        }
+        match self.last_locked {
+            Some((ref last, locked_at)) => {
+                let seconds = started.duration_since(locked_at).as_secs_f64().round() as i64;
+                let time = last.saturating_add(SignedDuration::from_secs(seconds));
+                telegram(&time, Status { free_running: true, ..Status::default() })
+            }
+            // Nothing better than the system clock yet
+            None => telegram(&started.to_zoned(TimeZone::UTC), Status { never_synced: true, free_running: true, ..Status::default() })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_telegram() {
+        let time: Zoned = "2016-01-01T00:00:00+01:00[+01:00]".parse().unwrap();
+        assert_eq!(telegram(&time, Status::default()), "\x02D:01.01.16;T:5;U:00.00.00;    \x03");
+        let summer: Zoned = "2016-03-27T03:00:00+02:00[+02:00]".parse().unwrap();
+        assert_eq!(telegram(&summer, Status { leap_second: true, ..Status::default() }), "\x02D:27.03.16;T:7;U:03.00.00;  SA\x03");
+        let msf: Zoned = "2016-01-01T00:00:00+00:00[+00:00]".parse().unwrap();
+        assert_eq!(telegram(&msf, Status { free_running: true, ..Status::default() }), "\x02D:01.01.16;T:5;U:00.00.00; *U \x03");
+    }
+
+    #[test]
+    fn test_free_running() {
+        let mut clock = MeinbergClock::new();
+        let started: Timestamp = "2016-01-01T00:00:00Z".parse().unwrap();
+        assert_eq!(clock.second(None, None, started), "\x02D:01.01.16;T:5;U:00.00.00;#*U \x03");
+
+        let time: Zoned = "2016-01-01T01:00:00+01:00[+01:00]".parse().unwrap();
+        clock.second(Some(&time), None, started);
+        let later = started + SignedDuration::from_millis(2_900);
+        assert_eq!(clock.second(None, None, later), "\x02D:01.01.16;T:5;U:01.00.03; *  \x03");
+    }
+}
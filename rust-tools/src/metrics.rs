@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use jiff::{SignedDuration, Timestamp};
+
+/* Prometheus metrics (text exposition format) over HTTP, for alerting on a
+ * fleet of receivers:
+ *
+ *      rust_tools_pulses_total{class}              pulses by their symbol
+ *      rust_tools_frames_total                     decoded frames
+ *      rust_tools_decoding_failures_total{kind}    by `DecodingFailure` variant
+ *      rust_tools_locked                           1 while the time of each second is known
+ *      rust_tools_seconds_since_last_frame         once a frame was decoded
+ *      rust_tools_offset_seconds                   last second mark against the system clock
+ *      rust_tools_pulse_threshold_milliseconds{class}
+ *
+ * Like `gpsd_server`, there are no threads: `poll` reads requests without
+ * blocking and answers complete ones from the read loop, so a scrape may
+ * take up to a second.
+ */
+
+pub const DEFAULT_PORT: u16 = 9477;
+
+// Connections that don't complete their request in time are dropped
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_REQUEST: usize = 8192;
+
+#[derive(Clone, Debug, Default)]
+pub struct Metrics {
+    pulses: BTreeMap<char, u64>,
+    frames: u64,
+    failures: BTreeMap<&'static str, u64>,
+    locked: bool,
+    last_frame: Option<Timestamp>,
+    offset: Option<SignedDuration>,
+    thresholds: Vec<(u32, char)>
+}
+
+impl Metrics {
+    // Every failure kind is exported from the start, even before it occurs
+    pub fn new(thresholds: Vec<(u32, char)>, failure_kinds: &[&'static str]) -> Metrics {
+        let failures = failure_kinds.iter().map(|kind| (*kind, 0)).collect();
+        Metrics { thresholds, failures, ..Metrics::default() }
+    }
+
+    pub fn pulse(&mut self, symbol: char) {
+        *self.pulses.entry(symbol).or_default() += 1;
+    }
+
+    pub fn frame(&mut self, received: Timestamp) {
+        self.frames += 1;
+        self.last_frame = Some(received);
+    }
+
+    pub fn failure(&mut self, kind: &'static str) {
+        *self.failures.entry(kind).or_default() += 1;
+    }
+
+    pub fn set_locked(&mut self, locked: bool) {
+        self.locked = locked;
+    }
+
+    pub fn set_offset(&mut self, offset: SignedDuration) {
+        self.offset = Some(offset);
+    }
+
+    pub fn render(&self, now: Timestamp) -> String {
+        let mut out = String::new();
+        header(&mut out, "pulses_total", "counter", "Pulses received, by the symbol they were classified as.");
+        for (class, count) in &self.pulses {
+            let _ = writeln!(out, "rust_tools_pulses_total{{class=\"{}\"}} {}", escape(*class), count);
+        }
+        header(&mut out, "frames_total", "counter", "Frames decoded.");
+        let _ = writeln!(out, "rust_tools_frames_total {}", self.frames);
+        header(&mut out, "decoding_failures_total", "counter", "Frames that failed to decode, by failure.");
+        for (kind, count) in &self.failures {
+            let _ = writeln!(out, "rust_tools_decoding_failures_total{{kind=\"{}\"}} {}", kind, count);
+        }
+        header(&mut out, "locked", "gauge", "Whether the time of each second is known.");
+        let _ = writeln!(out, "rust_tools_locked {}", u8::from(self.locked));
+        if let Some(last_frame) = self.last_frame {
+            header(&mut out, "seconds_since_last_frame", "gauge", "Seconds since the last decoded frame.");
+            let _ = writeln!(out, "rust_tools_seconds_since_last_frame {:.3}", now.duration_since(last_frame).as_secs_f64());
+        }
+        if let Some(offset) = self.offset {
+            header(&mut out, "offset_seconds", "gauge", "How far the system clock is behind the last second mark.");
+            let _ = writeln!(out, "rust_tools_offset_seconds {:.6}", offset.as_secs_f64());
+        }
+        header(&mut out, "pulse_threshold_milliseconds", "gauge", "Widest pulse of each class.");
+        for (limit, class) in &self.thresholds {
+            let _ = writeln!(out, "rust_tools_pulse_threshold_milliseconds{{class=\"{}\"}} {}", escape(*class), limit);
+        }
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP rust_tools_{} {}", name, help);
+    let _ = writeln!(out, "# TYPE rust_tools_{} {}", name, kind);
+}
+
+fn escape(class: char) -> String {
+    match class {
+        '"' | '\\' => format!("\\{}", class),
+        _ => class.to_string()
+    }
+}
+
+// An accepted connection, until its request is complete
+struct Scrape {
+    stream: TcpStream,
+    request: Vec<u8>,
+    accepted: Instant
+}
+
+impl Scrape {
+    // Read what arrived so far, whether the request is complete
+    fn read(&mut self) -> io::Result<bool> {
+        let mut buf = [0u8; 512];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(len) => self.request.extend_from_slice(&buf[..len]),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e)
+            }
+        }
+        Ok(self.request.windows(4).any(|end| end == b"\r\n\r\n") || self.request.len() > MAX_REQUEST)
+    }
+}
+
+pub struct MetricsServer {
+    listener: TcpListener,
+    scrapes: Vec<Scrape>
+}
+
+impl MetricsServer {
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<MetricsServer> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(MetricsServer { listener, scrapes: Vec::new() })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // Accept connections and answer complete requests, one per connection
+    pub fn poll(&mut self, metrics: &Metrics) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_ok() {
+                self.scrapes.push(Scrape { stream, request: Vec::new(), accepted: Instant::now() });
+            }
+        }
+        self.scrapes.retain_mut(|scrape| match scrape.read() {
+            // A failed answer only loses the scrape
+            Ok(true) => {
+                let _ = respond(&mut scrape.stream, &scrape.request, metrics);
+                false
+            }
+            Ok(false) => scrape.accepted.elapsed() < REQUEST_TIMEOUT,
+            Err(_) => false
+        });
+    }
+}
+
+fn respond(stream: &mut TcpStream, request: &[u8], metrics: &Metrics) -> io::Result<()> {
+    let request = String::from_utf8_lossy(request);
+    let mut words = request.split_whitespace();
+    let (status, body) = match (words.next(), words.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", metrics.render(Timestamp::now())),
+        (Some("GET"), _) => ("404 Not Found", String::from("See /metrics\n")),
+        _ => ("405 Method Not Allowed", String::new())
+    };
+    // The answer is small enough for the socket buffer of a fresh connection
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, body.len(), body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut metrics = Metrics::new(vec![(100, '0')], &["MissingBit", "ParityError"]);
+        let start: Timestamp = "2016-01-01T00:00:00Z".parse().unwrap();
+        metrics.pulse('0');
+        metrics.pulse('0');
+        metrics.pulse('_');
+        metrics.failure("ParityError");
+        metrics.frame(start);
+        metrics.set_locked(true);
+        metrics.set_offset(SignedDuration::from_millis(-3));
+
+        let text = metrics.render(start + SignedDuration::from_millis(1500));
+        let samples: Vec<&str> = text.lines().filter(|line| !line.starts_with('#')).collect();
+        assert_eq!(samples, [
+            "rust_tools_pulses_total{class=\"0\"} 2",
+            "rust_tools_pulses_total{class=\"_\"} 1",
+            "rust_tools_frames_total 1",
+            "rust_tools_decoding_failures_total{kind=\"MissingBit\"} 0",
+            "rust_tools_decoding_failures_total{kind=\"ParityError\"} 1",
+            "rust_tools_locked 1",
+            "rust_tools_seconds_since_last_frame 1.500",
+            "rust_tools_offset_seconds -0.003000",
+            "rust_tools_pulse_threshold_milliseconds{class=\"0\"} 100"
+        ]);
+        assert!(text.contains("# TYPE rust_tools_frames_total counter\n"));
+    }
+
+    #[test]
+    fn test_scrape() {
+        let mut server = MetricsServer::bind("127.0.0.1:0").expect("Could not bind");
+        let metrics = Metrics::new(Vec::new(), &[]);
+        let mut client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        client.write_all(b"GET /metrics HTTP/1.1\r\n").unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        // Half a request doesn't block the loop, the rest is read on the next poll
+        server.poll(&metrics);
+        client.write_all(b"Host: localhost\r\n\r\n").unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        server.poll(&metrics);
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("\nrust_tools_locked 0\n"));
+    }
+}
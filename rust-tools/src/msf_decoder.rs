@@ -0,0 +1,572 @@
+use std::convert::Infallible;
+use std::fmt::{self};
+use std::ops::Range;
+
+use jiff::{Zoned, civil::{Date, Weekday}, tz};
+use ringbuffer::{RingBuffer,AllocRingBuffer};
+
+use crate::bit_frame::{BitFrame, BitRange, FrameError};
+use crate::bitrep::*;
+use crate::century::{CenturyInference, CenturyPolicy};
+use crate::frame_view::FieldSpan;
+use crate::pulse_decoder::{Pulse, PulseTiming, WidthClasses};
+use crate::time_code::{self, DecodeError, DecodedFrame, LeapSecond, StationFailure, TimeCodeDecoder, decode_field, symbol_char};
+
+/* MSF (Anthorn, UK, 60 kHz), see e.g. https://en.wikipedia.org/wiki/Time_from_NPL_(MSF)
+ *
+ * Every second starts with the carrier switched off for 100ms, followed by
+ * two data bits A and B of 100ms each. The receiver pulse is thus
+ *      100ms       A=0, B=0
+ *      200ms       A=1, B=0
+ *      300ms       A=1, B=1
+ *      100ms + 100ms gap + 100ms       A=0, B=1
+ *      500ms       minute marker (second 0)
+ * Like DCF77, the code announces the minute starting at the next marker.
+ *
+ * Second       A                           B
+ * 0            minute marker
+ * 1  - 8       spare                       DUT1 positive (unary)
+ * 9  - 16      spare                       DUT1 negative (unary)
+ * 17 - 24      BCD (msb first) Year        -
+ * 25 - 29      BCD (msb first) Month       -
+ * 30 - 35      BCD (msb first) Day         -
+ * 36 - 38      Day of week (0: Sunday)     -
+ * 39 - 44      BCD (msb first) Hour        -
+ * 45 - 51      BCD (msb first) Minute      -
+ * 52           0                           -
+ * 53           1                           BST change imminent
+ * 54           1                           Year parity (odd, 17A - 24A)
+ * 55           1                           Date parity (odd, 25A - 35A)
+ * 56           1                           Day of week parity (odd, 36A - 38A)
+ * 57           1                           Time parity (odd, 39A - 51A)
+ * 58           1                           BST in effect
+ * 59           0                           -
+ *
+ * Measuring pulses up to 300ms needs a lower baud rate than DCF77, we assume
+ * 25 baud (40ms per bit, saturating at 360ms).
+ */
+pub const PULSE_TIMING: PulseTiming = PulseTiming { bit_ms: 40, period_ms: 1000 };
+
+const MINUTE_IDENTIFIER: u8 = 0b0111_1110;
+
+// On the B bits
+const DUT1_POSITIVE: Range<usize> = 1..9;
+const DUT1_NEGATIVE: Range<usize> = 9..17;
+
+// The A bits, the B bits carry DUT1, parities and flags
+#[derive(Clone, Debug, PartialEq, BitFrame)]
+#[frame(len = 60)]
+#[fixed(name = "minute_marker", bits = 0, label = "M", skipped)]
+#[fixed(name = "spare", bits = 1..17, label = "_")]
+pub struct MsfFields {
+    #[field(bits = 17..25, bcd = [4, 4], msb, labels = ["10y", "year"])]
+    pub year: i16,
+    #[field(bits = 25..30, bcd = [1, 4], msb, labels = ["10m", "mon"])]
+    pub month: i16,
+    #[field(bits = 30..36, bcd = [2, 4], msb, labels = ["10d", "day"])]
+    pub day: i16,
+    #[field(bits = 36..39, bcd = [3], msb, labels = ["dow"])]
+    pub weekday: i16,
+    #[field(bits = 39..45, bcd = [2, 4], msb, labels = ["10h", "hour"])]
+    pub hour: i16,
+    #[field(bits = 45..52, bcd = [3, 4], msb, labels = ["10m", "min"])]
+    pub minute: i16,
+    #[field(bits = 52..60, raw, label = "id")]
+    pub identifier: [Bit; 8]
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MsfSymbol {
+    Marker,
+    Bits { a: bool, b: bool },
+    Unknown,
+    Skipped
+}
+
+impl MsfSymbol {
+    pub fn a(&self) -> Bit {
+        match *self {
+            Self::Bits { a, .. } => Bit::Value(a),
+            Self::Skipped => Bit::Skipped,
+            _ => Bit::Unknown
+        }
+    }
+
+    pub fn b(&self) -> Bit {
+        match *self {
+            Self::Bits { b, .. } => Bit::Value(b),
+            Self::Skipped => Bit::Skipped,
+            _ => Bit::Unknown
+        }
+    }
+
+    // Inverse of `Display`
+    pub fn from_char(c: char) -> Option<MsfSymbol> {
+        match c {
+            'M' => Some(Self::Marker),
+            '?' => Some(Self::Unknown),
+            '_' => Some(Self::Skipped),
+            _ => c.to_digit(10).filter(|v| *v < 4).map(|v| Self::Bits { a: v & 1 != 0, b: v & 2 != 0 })
+        }
+    }
+}
+
+impl fmt::Display for MsfSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Marker            => write!(f, "M"),
+            Self::Bits { a, b }     => write!(f, "{}", u8::from(a) + 2 * u8::from(b)),
+            Self::Unknown           => write!(f, "?"),
+            Self::Skipped           => write!(f, "_"),
+        }
+    }
+}
+
+const PULSE_CLASSES: WidthClasses<MsfSymbol> = WidthClasses {
+    classes: &[
+        (150, MsfSymbol::Bits { a: false, b: false }),
+        (250, MsfSymbol::Bits { a: true, b: false }),
+        (400, MsfSymbol::Bits { a: true, b: true }),
+    ],
+    longer: MsfSymbol::Marker
+};
+
+pub fn decode_pulse(pulse: u8) -> MsfSymbol {
+    match PULSE_TIMING.measure(pulse) {
+        Pulse::None => MsfSymbol::Skipped,
+        Pulse::Invalid => MsfSymbol::Unknown,
+        Pulse::Single { saturated: true, .. } => MsfSymbol::Marker,
+        Pulse::Single { width_ms, .. } => PULSE_CLASSES.classify(width_ms),
+        Pulse::Double { first_ms, second_ms, .. } if first_ms <= 150 && second_ms <= 150 =>
+            MsfSymbol::Bits { a: false, b: true },
+        Pulse::Double { .. } => MsfSymbol::Unknown
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParityGroup {
+    Year,
+    Date,
+    Weekday,
+    Time
+}
+
+impl ParityGroup {
+    // The A bits covered, and the B bit holding the parity
+    pub fn bit_range(&self) -> (Range<usize>, usize) {
+        match *self {
+            Self::Year      => (MsfFields::YEAR_BITS, 54),
+            Self::Date      => (MsfFields::MONTH_BITS.start..MsfFields::DAY_BITS.end, 55),
+            Self::Weekday   => (MsfFields::WEEKDAY_BITS, 56),
+            Self::Time      => (MsfFields::HOUR_BITS.start..MsfFields::MINUTE_BITS.end, 57),
+        }
+    }
+}
+
+impl fmt::Display for ParityGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match *self {
+            Self::Year      => "year",
+            Self::Date      => "date",
+            Self::Weekday   => "day of week",
+            Self::Time      => "time",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    Year,
+    Month,
+    Day,
+    Weekday,
+    Hour,
+    Minute
+}
+
+impl Field {
+    pub fn bit_range(&self) -> Range<usize> {
+        match *self {
+            Self::Year      => MsfFields::YEAR_BITS,
+            Self::Month     => MsfFields::MONTH_BITS,
+            Self::Day       => MsfFields::DAY_BITS,
+            Self::Weekday   => MsfFields::WEEKDAY_BITS,
+            Self::Hour      => MsfFields::HOUR_BITS,
+            Self::Minute    => MsfFields::MINUTE_BITS,
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match *self {
+            Self::Year      => "year",
+            Self::Month     => "month",
+            Self::Day       => "day",
+            Self::Weekday   => "day of week",
+            Self::Hour      => "hour",
+            Self::Minute    => "minute",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodingFailure {
+    NotEnoughBits,
+    NotSync,    /* No minute marker at second 0 */
+    MissingMinuteIdentifier,
+    MissingBit(usize),
+    ParityError(ParityGroup),
+    InvalidDigit(Field, u8),
+    OutOfRange(Field, i16)
+}
+
+impl DecodingFailure {
+    pub const KINDS: [&'static str; 7] = ["NotEnoughBits", "NotSync", "MissingMinuteIdentifier", "MissingBit", "ParityError", "InvalidDigit", "OutOfRange"];
+}
+
+impl StationFailure for DecodingFailure {
+    type Field = Field;
+    type Parity = Infallible;
+
+    fn kind(&self) -> &'static str {
+        match *self {
+            Self::NotEnoughBits           => "NotEnoughBits",
+            Self::NotSync                 => "NotSync",
+            Self::MissingMinuteIdentifier => "MissingMinuteIdentifier",
+            Self::MissingBit(_)           => "MissingBit",
+            Self::ParityError(_)          => "ParityError",
+            Self::InvalidDigit(..)        => "InvalidDigit",
+            Self::OutOfRange(..)          => "OutOfRange",
+        }
+    }
+
+    fn is_not_sync(&self) -> bool {
+        *self == Self::NotSync
+    }
+
+    fn field_failure(field: Field, error: FrameError<Infallible>) -> Self {
+        match error {
+            FrameError::NotEnoughBits           => Self::NotEnoughBits,
+            FrameError::NotSync                 => Self::NotSync,
+            FrameError::MissingBit(n)           => Self::MissingBit(n),
+            FrameError::ParityError(never)      => match never {},
+            FrameError::InvalidDigit(_, value)  => Self::InvalidDigit(field, value),
+            FrameError::InvalidValue(_, value)  => Self::OutOfRange(field, value as i16),
+        }
+    }
+
+    fn out_of_range(field: Field, value: i16) -> Self {
+        Self::OutOfRange(field, value)
+    }
+}
+
+impl fmt::Display for DecodingFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::NotEnoughBits             => write!(f, "not enough seconds for a full minute"),
+            Self::NotSync                   => write!(f, "no minute marker at second 0"),
+            Self::MissingMinuteIdentifier   => write!(f, "invalid minute identifier (seconds 52-59)"),
+            Self::MissingBit(n)             => write!(f, "second {} is missing", n),
+            Self::ParityError(group)        => {
+                let (bits, parity) = group.bit_range();
+                write!(f, "{} parity error ({}, parity bit {}B)", group, BitRange(bits), parity)
+            },
+            Self::InvalidDigit(field, v)    =>
+                write!(f, "invalid {} digit {} ({})", field, v, BitRange(field.bit_range())),
+            Self::OutOfRange(field, v)      =>
+                write!(f, "{} {} out of range ({})", field, v, BitRange(field.bit_range())),
+        }
+    }
+}
+
+pub type Error = DecodeError<DecodingFailure>;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MsfFrame {
+    pub time: Zoned,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_util::option_weekday"))]
+    pub weekday: Option<Weekday>,
+    pub century: CenturyInference,
+    // UT1 - UTC in tenths of a second
+    pub dut1: i8,
+    pub bst: bool,
+    pub bst_change_imminent: bool
+}
+
+// The A bits, markers as skipped
+fn a_bits(symbols: &[MsfSymbol]) -> Vec<Bit> {
+    symbols.iter()
+        .map(|s| if *s == MsfSymbol::Marker { Bit::Skipped } else { s.a() })
+        .collect()
+}
+
+fn missing_bit(symbols: &[MsfSymbol], range: Range<usize>) -> DecodingFailure {
+    let n = range.clone()
+        .find(|n| symbols[*n].a().to_bit().is_none())
+        .unwrap_or(range.start);
+    DecodingFailure::MissingBit(n)
+}
+
+fn check_parity(group: ParityGroup, symbols: &[MsfSymbol]) -> Result<()> {
+    let (range, parity_bit) = group.bit_range();
+    let mut parity = symbols[parity_bit].b().to_bit()
+        .ok_or(DecodingFailure::MissingBit(parity_bit))?;
+    for n in range.clone() {
+        parity ^= symbols[n].a().to_bit()
+            .ok_or_else(|| missing_bit(symbols, range.clone()))?;
+    }
+    if parity { Ok(()) }
+    else { Err(From::from(DecodingFailure::ParityError(group))) }
+}
+
+// The unary DUT1 bits that are set
+fn count_ones(symbols: &[MsfSymbol]) -> i8 {
+    symbols.iter()
+        .filter(|symbol| symbol.b() == Bit::Value(true))
+        .count() as i8
+}
+
+pub struct Decoder {
+    buffer: AllocRingBuffer<MsfSymbol>,
+    century: CenturyPolicy
+}
+
+impl fmt::Display for Decoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&self.buffer.iter().map(|x| x.to_string()).collect::<String>())
+    }
+}
+
+impl Decoder {
+    pub fn new() -> Decoder {
+        Decoder {
+            buffer: AllocRingBuffer::new(60),
+            century: CenturyPolicy::default()
+        }
+    }
+
+    pub fn with_century(mut self, century: CenturyPolicy) -> Decoder {
+        self.century = century;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.buffer.is_full()
+    }
+
+    pub fn add_symbol(&mut self, value: MsfSymbol) -> &Self {
+        self.buffer.enqueue(value);
+        self
+    }
+
+    pub fn add_pulse(&mut self, pulse: u8) -> &Self {
+        self.add_symbol(decode_pulse(pulse))
+    }
+
+    pub fn add_symbol_iter(&mut self, iter: impl IntoIterator<Item = MsfSymbol>) -> &Self {
+        for symbol in iter {
+            self.add_symbol(symbol);
+        }
+        self
+    }
+
+    pub fn decode_msf(&self) -> Result<Zoned> {
+        self.decode_frame().map(|frame| frame.time)
+    }
+
+    pub fn decode_frame(&self) -> Result<MsfFrame> {
+        if !self.buffer.is_full() {
+            return Err(From::from(DecodingFailure::NotEnoughBits))
+        }
+        let symbols = self.buffer.to_vec();
+        if symbols[0] != MsfSymbol::Marker {
+            return Err(From::from(DecodingFailure::NotSync))
+        }
+        let bits = a_bits(&symbols);
+        let identifier: Option<u8> = FromBits::from_maybebits_msb(&bits[MsfFields::IDENTIFIER_BITS]);
+        if identifier != Some(MINUTE_IDENTIFIER) {
+            return Err(From::from(DecodingFailure::MissingMinuteIdentifier))
+        }
+
+        check_parity(ParityGroup::Year, &symbols)?;
+        let year = decode_field(Field::Year, MsfFields::decode_year(&bits), 0..100)? as i8;
+        check_parity(ParityGroup::Date, &symbols)?;
+        let month = decode_field(Field::Month, MsfFields::decode_month(&bits), 1..13)? as i8;
+        let day = decode_field(Field::Day, MsfFields::decode_day(&bits), 1..32)? as i8;
+        check_parity(ParityGroup::Weekday, &symbols)?;
+        let weekday_number = decode_field(Field::Weekday, MsfFields::decode_weekday(&bits), 0..7)? as i8;
+        let weekday = Weekday::from_sunday_zero_offset(weekday_number).ok();
+        check_parity(ParityGroup::Time, &symbols)?;
+        let hour = decode_field(Field::Hour, MsfFields::decode_hour(&bits), 0..24)? as i8;
+        let minute = decode_field(Field::Minute, MsfFields::decode_minute(&bits), 0..60)? as i8;
+
+        let bst = symbols[58].b() == Bit::Value(true);
+        let bst_change_imminent = symbols[53].b() == Bit::Value(true);
+        let dut1 = count_ones(&symbols[DUT1_POSITIVE]) - count_ones(&symbols[DUT1_NEGATIVE]);
+
+        let (full_year, century) = self.century.full_year(year, month, day, weekday);
+        let date = Date::new(full_year, month, day)
+            .map_err(|_| DecodingFailure::OutOfRange(Field::Day, day as i16))?;
+        let offset = tz::offset(if bst { 1 } else { 0 });
+        let time = date.at(hour, minute, 0, 0)
+            .to_zoned(offset.to_time_zone())?;
+        Ok(MsfFrame { time, weekday, century, dut1, bst, bst_change_imminent })
+    }
+}
+
+impl TimeCodeDecoder for Decoder {
+    fn name(&self) -> &'static str {
+        "msf"
+    }
+
+    fn pulse_timing(&self) -> PulseTiming {
+        PULSE_TIMING
+    }
+
+    fn failure_kinds(&self) -> Vec<&'static str> {
+        time_code::failure_kinds(&DecodingFailure::KINDS)
+    }
+
+    fn pulse_thresholds(&self) -> Vec<(u32, char)> {
+        time_code::width_thresholds(&PULSE_CLASSES)
+    }
+
+    fn frame_len(&self) -> usize {
+        60
+    }
+
+    fn alphabet(&self) -> &'static str {
+        "0123M?_"
+    }
+
+    fn layout(&self) -> &'static [FieldSpan] {
+        MsfFields::LAYOUT
+    }
+
+    fn feed_pulse(&mut self, pulse: u8) -> char {
+        let symbol = decode_pulse(pulse);
+        self.add_symbol(symbol);
+        symbol_char(symbol)
+    }
+
+    fn feed_symbol(&mut self, symbol: char) -> bool {
+        let Some(symbol) = MsfSymbol::from_char(symbol) else { return false };
+        self.add_symbol(symbol);
+        true
+    }
+
+    fn is_synced(&self) -> bool {
+        self.buffer.is_full() && self.buffer[0] == MsfSymbol::Marker
+    }
+
+    fn frame_bits(&self) -> Vec<Bit> {
+        a_bits(&self.buffer.to_vec())
+    }
+
+    fn decode(&self) -> time_code::Result<DecodedFrame> {
+        let frame = self.decode_frame()?;
+        // MSF does not announce leap seconds
+        Ok(DecodedFrame {
+            next_frame: frame.time.clone(),
+            time: frame.time,
+            century: frame.century,
+            leap_second: LeapSecond::None,
+            summer_time: frame.bst,
+            summer_time_change: frame.bst_change_imminent
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2016-01-01 (Friday) 00:00 GMT, DUT1 = -0.2s
+    fn build_valid_signal() -> Vec<MsfSymbol> {
+        let fields = MsfFields {
+            year: 16,
+            month: 1,
+            day: 1,
+            weekday: 5,
+            hour: 0,
+            minute: 0,
+            identifier: FromBits::from_bits_msb([false, true, true, true, true, true, true, false])
+        };
+        let a = fields.encode_bits().expect("Could not encode the A bits");
+        let mut b = vec![false; 60];
+        b[9] = true;    // DUT1: two negative
+        b[10] = true;
+        b[55] = true;   // date: 0 0001 00 0001
+        b[56] = true;   // day of week: 101
+        b[57] = true;   // time: all zero
+
+        a.into_iter().zip(b)
+            .map(|(a, b)| match a {
+                Bit::Skipped => MsfSymbol::Marker,
+                // Spare bits are left unknown
+                a => MsfSymbol::Bits { a: a == Bit::Value(true), b }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_decode_pulse() {
+        assert_eq!(decode_pulse(0b0), MsfSymbol::Skipped);
+        assert_eq!(decode_pulse(0b1), MsfSymbol::Bits { a: false, b: false });
+        assert_eq!(decode_pulse(0b1111), MsfSymbol::Bits { a: true, b: false });
+        assert_eq!(decode_pulse(0b11_1111), MsfSymbol::Bits { a: true, b: true });
+        assert_eq!(decode_pulse(0b0111_0001), MsfSymbol::Bits { a: false, b: true });
+        assert_eq!(decode_pulse(0xFF), MsfSymbol::Marker);
+        assert_eq!(decode_pulse(0b0110), MsfSymbol::Unknown);
+    }
+
+    #[test]
+    fn test_decoder_valid_signal() {
+        let signal = build_valid_signal();
+        let mut decoder = Decoder::new();
+        decoder.add_symbol_iter(signal[0..59].iter().copied());
+        assert!(matches!(decoder.decode_msf(),
+            Err(Error::DecodingError(DecodingFailure::NotEnoughBits))));
+
+        decoder.add_symbol(signal[59]);
+        println!("{}", decoder);
+        let frame = decoder.decode_frame()
+            .expect("Could not decode msf time");
+        assert_eq!(frame.time.to_string(), "2016-01-01T00:00:00+00:00[UTC]");
+        assert_eq!(frame.weekday, Some(Weekday::Friday));
+        assert_eq!(frame.dut1, -2);
+        assert!(!frame.bst);
+
+        // Shifted by one second, there is no marker
+        decoder.add_symbol(signal[0]);
+        assert!(matches!(decoder.decode_msf(),
+            Err(Error::DecodingError(DecodingFailure::NotSync))));
+    }
+
+    #[test]
+    fn test_decoder_parity() {
+        let mut signal = build_valid_signal();
+        signal[58] = MsfSymbol::Bits { a: true, b: true };
+        signal[45] = MsfSymbol::Bits { a: true, b: false };
+        let mut decoder = Decoder::new();
+        decoder.add_symbol_iter(signal.iter().copied());
+        let error = decoder.decode_msf().unwrap_err();
+        assert!(matches!(error, Error::DecodingError(DecodingFailure::ParityError(ParityGroup::Time))));
+        assert_eq!(error.to_string(), "time parity error (bits 39-51, parity bit 57B)");
+
+        signal[57] = MsfSymbol::Bits { a: true, b: false };
+        decoder.add_symbol_iter(signal.iter().copied());
+        let frame = decoder.decode_frame()
+            .expect("Could not decode msf time");
+        assert_eq!(frame.time.to_string(), "2016-01-01T00:40:00+01:00[+01:00]");
+        assert!(frame.bst);
+    }
+}
@@ -0,0 +1,52 @@
+use jiff::{Zoned, tz::TimeZone};
+
+/* NMEA 0183 time sentences, for equipment that expects a GPS receiver:
+ *
+ *      $GPZDA,hhmmss.ss,dd,mm,yyyy,zh,zm*cs        date and time (UTC)
+ *      $GPRMC,hhmmss.ss,A,,,,,,,ddmmyy,,*cs        minimum data, no position
+ *
+ * Sent every second. RMC is valid (A) while the decoder is locked, the time
+ * is unknown otherwise: RMC is sent void (V) and empty, ZDA not at all.
+ * See `serial_output` for the port.
+ */
+
+pub const DEFAULT_BAUD_RATE: u32 = 4800;
+
+pub fn checksum(body: &str) -> u8 {
+    body.bytes().fold(0, |sum, c| sum ^ c)
+}
+
+// `body` without the leading $
+pub fn sentence(body: &str) -> String {
+    format!("${}*{:02X}\r\n", body, checksum(body))
+}
+
+pub fn zda(time: &Zoned) -> String {
+    let utc = time.with_time_zone(TimeZone::UTC);
+    sentence(&format!("GPZDA,{},00,00", utc.strftime("%H%M%S.00,%d,%m,%Y")))
+}
+
+pub fn rmc(time: Option<&Zoned>) -> String {
+    match time {
+        Some(time) => {
+            let utc = time.with_time_zone(TimeZone::UTC);
+            sentence(&format!("GPRMC,{},A,,,,,,,{},,", utc.strftime("%H%M%S.00"), utc.strftime("%d%m%y")))
+        }
+        None => sentence("GPRMC,,V,,,,,,,,,")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sentences() {
+        let time: Zoned = "2016-01-01T00:00:00+01:00[+01:00]".parse().unwrap();
+        assert_eq!(zda(&time), "$GPZDA,230000.00,31,12,2015,00,00*60\r\n");
+        assert_eq!(rmc(Some(&time)), "$GPRMC,230000.00,A,,,,,,,311215,,*0C\r\n");
+        assert_eq!(rmc(None), "$GPRMC,,V,,,,,,,,,*31\r\n");
+        // From the NMEA 0183 examples
+        assert_eq!(checksum("GPGLL,5300.97914,N,00259.98174,E,125926,A"), 0x28);
+    }
+}
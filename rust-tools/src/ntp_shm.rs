@@ -0,0 +1,176 @@
+use std::io;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{Ordering, fence};
+
+use jiff::Timestamp;
+
+use crate::time_code::{LeapSecond, RefclockSample};
+
+/* NTP shared memory reference clock, as read by ntpd (driver 28, "SHM"),
+ * ntpsec and chrony (`refclock SHM`). Each unit is a System V shared memory
+ * segment with the key "NTP0" + unit, holding one sample:
+ *
+ *      clock       the time the sample stands for, here a minute mark
+ *      receive     the system time it was received at
+ *
+ * We write in mode 1: the reader takes a sample only if `valid` is set and
+ * `count` did not change while it was copying, so a sample is never read
+ * half-written. Like ntpd, units 0 and 1 are created for root only, all
+ * others for everybody.
+ */
+
+// "NTP0"
+const SHM_KEY_BASE: libc::key_t = 0x4e54_5030;
+
+// About the 20ms resolution of our pulse measurement at 50 baud
+pub const DEFAULT_PRECISION: i32 = -6;
+
+// Layout of `struct shmTime` in ntpd's refclock_shm.c
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+struct ShmTime {
+    mode: libc::c_int,
+    count: libc::c_int,
+    clock_sec: libc::time_t,
+    clock_usec: libc::c_int,
+    receive_sec: libc::time_t,
+    receive_usec: libc::c_int,
+    leap: libc::c_int,
+    precision: libc::c_int,
+    nsamples: libc::c_int,
+    valid: libc::c_int,
+    clock_nsec: libc::c_uint,
+    receive_nsec: libc::c_uint,
+    dummy: [libc::c_int; 8]
+}
+
+const LEAP_NOWARNING: libc::c_int = 0;
+const LEAP_ADDSECOND: libc::c_int = 1;
+const LEAP_DELSECOND: libc::c_int = 2;
+
+pub struct ShmRefclock {
+    unit: u8,
+    time: NonNull<ShmTime>,
+    precision: i32
+}
+
+impl ShmRefclock {
+    pub fn attach(unit: u8, precision: i32) -> io::Result<ShmRefclock> {
+        let permissions = if unit < 2 { 0o600 } else { 0o666 };
+        let id = segment(SHM_KEY_BASE + libc::key_t::from(unit), permissions)?;
+        let mut refclock = Self::attach_segment(id, precision)?;
+        refclock.unit = unit;
+        Ok(refclock)
+    }
+
+    fn attach_segment(id: libc::c_int, precision: i32) -> io::Result<ShmRefclock> {
+        // SAFETY: plain system call, the segment is at least as large as `ShmTime`
+        let address = unsafe { libc::shmat(id, ptr::null(), 0) };
+        if address as isize == -1 {
+            return Err(io::Error::last_os_error())
+        }
+        let time = NonNull::new(address.cast()).ok_or_else(io::Error::last_os_error)?;
+        Ok(ShmRefclock { unit: 0, time, precision })
+    }
+
+    pub fn unit(&self) -> u8 {
+        self.unit
+    }
+
+    pub fn publish(&mut self, sample: &RefclockSample) {
+        let shm = self.time.as_ptr();
+        let leap = match sample.leap_second {
+            LeapSecond::None => LEAP_NOWARNING,
+            LeapSecond::Inserted => LEAP_ADDSECOND,
+            LeapSecond::Deleted => LEAP_DELSECOND,
+        };
+        let (clock_sec, clock_nsec) = split(sample.clock);
+        let (receive_sec, receive_nsec) = split(sample.receive);
+        // SAFETY: the segment stays attached while we live, other processes
+        // only read it, and the count/valid protocol tells them when to retry
+        unsafe {
+            ptr::addr_of_mut!((*shm).valid).write_volatile(0);
+            let count = ptr::addr_of_mut!((*shm).count);
+            count.write_volatile(count.read_volatile().wrapping_add(1));
+            fence(Ordering::SeqCst);
+
+            ptr::addr_of_mut!((*shm).mode).write_volatile(1);
+            ptr::addr_of_mut!((*shm).clock_sec).write_volatile(clock_sec);
+            ptr::addr_of_mut!((*shm).clock_usec).write_volatile((clock_nsec / 1000) as libc::c_int);
+            ptr::addr_of_mut!((*shm).clock_nsec).write_volatile(clock_nsec);
+            ptr::addr_of_mut!((*shm).receive_sec).write_volatile(receive_sec);
+            ptr::addr_of_mut!((*shm).receive_usec).write_volatile((receive_nsec / 1000) as libc::c_int);
+            ptr::addr_of_mut!((*shm).receive_nsec).write_volatile(receive_nsec);
+            ptr::addr_of_mut!((*shm).leap).write_volatile(leap);
+            ptr::addr_of_mut!((*shm).precision).write_volatile(self.precision);
+
+            fence(Ordering::SeqCst);
+            count.write_volatile(count.read_volatile().wrapping_add(1));
+            ptr::addr_of_mut!((*shm).valid).write_volatile(1);
+        }
+    }
+}
+
+impl Drop for ShmRefclock {
+    fn drop(&mut self) {
+        // The segment itself stays, the time server keeps it attached
+        unsafe { libc::shmdt(self.time.as_ptr().cast()) };
+    }
+}
+
+fn segment(key: libc::key_t, permissions: libc::c_int) -> io::Result<libc::c_int> {
+    // SAFETY: plain system call
+    let id = unsafe { libc::shmget(key, size_of::<ShmTime>(), libc::IPC_CREAT | permissions) };
+    if id < 0 {
+        return Err(io::Error::last_os_error())
+    }
+    Ok(id)
+}
+
+fn split(time: Timestamp) -> (libc::time_t, libc::c_uint) {
+    (time.as_second() as libc::time_t, time.subsec_nanosecond() as libc::c_uint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // What ntpd does in mode 1
+    fn read_sample(refclock: &ShmRefclock) -> Option<ShmTime> {
+        let shm = refclock.time.as_ptr();
+        unsafe {
+            let count = ptr::addr_of!((*shm).count).read_volatile();
+            let copy = shm.read_volatile();
+            fence(Ordering::SeqCst);
+            if copy.mode != 1 || copy.valid == 0 || count != ptr::addr_of!((*shm).count).read_volatile() {
+                return None
+            }
+            ptr::addr_of_mut!((*shm).valid).write_volatile(0);
+            Some(copy)
+        }
+    }
+
+    #[test]
+    fn test_publish() {
+        let id = segment(libc::IPC_PRIVATE, 0o600)
+            .expect("Could not create shared memory segment");
+        let mut refclock = ShmRefclock::attach_segment(id, -6)
+            .expect("Could not attach shared memory segment");
+        // Removed once detached
+        unsafe { libc::shmctl(id, libc::IPC_RMID, ptr::null_mut()) };
+        assert!(read_sample(&refclock).is_none());
+
+        let clock: Timestamp = "2016-01-01T00:00:00Z".parse().unwrap();
+        let receive: Timestamp = "2016-01-01T00:00:00.0125Z".parse().unwrap();
+        refclock.publish(&RefclockSample { clock, receive, leap_second: LeapSecond::Inserted });
+        let sample = read_sample(&refclock).expect("No valid sample");
+        assert_eq!(sample.clock_sec, clock.as_second() as libc::time_t);
+        assert_eq!((sample.clock_usec, sample.clock_nsec), (0, 0));
+        assert_eq!(sample.receive_sec, clock.as_second() as libc::time_t);
+        assert_eq!((sample.receive_usec, sample.receive_nsec), (12_500, 12_500_000));
+        assert_eq!((sample.leap, sample.precision), (LEAP_ADDSECOND, -6));
+        assert_eq!(sample.count, 2);
+        // Taken by the reader
+        assert!(read_sample(&refclock).is_none());
+    }
+}
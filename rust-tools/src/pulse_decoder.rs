@@ -1,20 +1,105 @@
 use crate::bitrep::Bit;
 
+/* Pulses are measured by the UART: the receiver output drives RX, the start
+    of a pulse looks like a start bit, and the rest of the pulse shows up as
+    ones (after inverting) in the received byte, starting from the lsb.
+    The first bit interval is "consumed" by the UART as the start bit, so
+        pulse length = bit_ms * (count(bits) + 1)
+    Pulses longer than the whole byte (start bit + 8 data bits) saturate.
+    A new pulse starts every `period_ms`.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PulseTiming {
+    pub bit_ms: u32,
+    pub period_ms: u32
+}
+
+// 50 baud, 20ms per bit: enough for DCF77 (100ms / 200ms), one pulse per second
+pub const BAUD_50: PulseTiming = PulseTiming { bit_ms: 20, period_ms: 1000 };
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+// Internally tagged for the event log, serde needs alloc for that
+#[cfg_attr(all(feature = "serde", feature = "std"), serde(tag = "kind"))]
+pub enum Pulse {
+    None,
+    Invalid,
+    Single { width_ms: u32, saturated: bool },
+    // Two pulses within one byte, e.g. MSF "A=0, B=1" seconds
+    Double { first_ms: u32, gap_ms: u32, second_ms: u32 }
+}
+
+impl PulseTiming {
+    // A byte is read after its stop bit, 10 bit intervals after the pulse started
+    pub fn byte_ms(&self) -> u32 {
+        self.bit_ms * 10
+    }
+
+    pub fn measure(&self, pulse: u8) -> Pulse {
+        if pulse == 0 {
+            return Pulse::None
+        }
+        let first = pulse.trailing_ones();
+        let rest = pulse.checked_shr(first).unwrap_or(0);
+        if rest == 0 {
+            return Pulse::Single {
+                width_ms: self.bit_ms * (first + 1),
+                saturated: first == 8
+            }
+        }
+        if first == 0 {
+            // Does not start with the start bit
+            return Pulse::Invalid
+        }
+        let gap = rest.trailing_zeros();
+        let second_bits = rest >> gap;
+        let second = second_bits.trailing_ones();
+        if second_bits >> second != 0 {
+            return Pulse::Invalid
+        }
+        Pulse::Double {
+            first_ms: self.bit_ms * (first + 1),
+            gap_ms: self.bit_ms * gap,
+            second_ms: self.bit_ms * second
+        }
+    }
+}
+
+/* Classification of single pulses by width: the first class whose upper
+    limit is not exceeded wins, anything longer (including saturated pulses)
+    gets the `longer` symbol.
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct WidthClasses<S: Copy + 'static> {
+    pub classes: &'static [(u32, S)],
+    pub longer: S
+}
+
+impl<S: Copy + 'static> WidthClasses<S> {
+    pub fn classify(&self, width_ms: u32) -> S {
+        self.classes.iter()
+            .find(|(limit, _)| width_ms <= *limit)
+            .map_or(self.longer, |(_, symbol)| *symbol)
+    }
+}
+
+// DCF77: 100ms = 0, 200ms = 1
+pub(crate) const DCF77_CLASSES: WidthClasses<Bit> = WidthClasses {
+    classes: &[(100, Bit::Value(false))],
+    longer: Bit::Value(true)
+};
+
 pub fn decode_pulse(pulse: u8) -> Bit {
-    // Check that "pulsed" is a series of ones starting from lsb
-    if pulse.wrapping_add(1) & pulse >= 1 {
-        return Bit::Unknown
-    } else if pulse == 0 {
-        return Bit::Skipped
-    } else {
-        /* We assume 50baud, i.e. 1 bit represents an interval of 20ms
-            The first 20ms are "consumed" by the UART as the start bit,
-            so the pulse length = 20ms * (count(bits) + 1).
-            We want to check if the pulse is longer than 100ms, so we check
-                100ms < 20ms * (count(bits) + 1)
-            <=>     4 < count(bits)
-         */
-        return Bit::Value(pulse > 0xF)
+    /* We assume 50baud, i.e. 1 bit represents an interval of 20ms
+        We want to check if the pulse is longer than 100ms, so we check
+            100ms < 20ms * (count(bits) + 1)
+        <=>     4 < count(bits)
+     */
+    match BAUD_50.measure(pulse) {
+        Pulse::None => Bit::Skipped,
+        Pulse::Single { width_ms, .. } => DCF77_CLASSES.classify(width_ms),
+        _ => Bit::Unknown
     }
 }
 
@@ -38,4 +123,14 @@ mod tests {
         assert_eq!(decode_pulse(0b111111), Bit::Value(true));
         assert_eq!(decode_pulse(0xFF), Bit::Value(true));
     }
+
+    #[test]
+    fn test_measure() {
+        let timing = PulseTiming { bit_ms: 40, period_ms: 1000 };
+        assert_eq!(timing.measure(0b11), Pulse::Single { width_ms: 120, saturated: false });
+        assert_eq!(timing.measure(0xFF), Pulse::Single { width_ms: 360, saturated: true });
+        assert_eq!(timing.measure(0b0111_0001), Pulse::Double { first_ms: 80, gap_ms: 120, second_ms: 120 });
+        assert_eq!(timing.measure(0b0101_0001), Pulse::Invalid);
+        assert_eq!(timing.measure(0b1110), Pulse::Invalid);
+    }
 }
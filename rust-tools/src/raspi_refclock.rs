@@ -11,7 +11,9 @@ pub fn list_serial_ports() {
     }
 }
 
-pub fn setup_serial() -> TTYPort{
+pub const DEFAULT_PORT: &str = "/dev/ttyAMA0";
+
+pub fn setup_serial(port_name: &str, baud_rate: u32) -> TTYPort{
     /* FIXME:
         See termios documentation: https://man7.org/linux/man-pages/man3/termios.3.html
         Termios flags set by
@@ -24,7 +26,7 @@ pub fn setup_serial() -> TTYPort{
             termios.c_cflag |= libc::CREAD | libc::CLOCAL;
 
      */
-    let port = serialport::new("/dev/ttyAMA0", 50)
+    serialport::new(port_name, baud_rate)
         .timeout(Duration::from_millis(100))
         // effectively sets c_cflag |= CS8
         .data_bits(DataBits::Eight)
@@ -36,6 +38,5 @@ pub fn setup_serial() -> TTYPort{
         // .dtr_on_open(false)
         .open_native()
         // .map_err(|ref e| format!("Port '{}' not available: {}", &port_name, e))?;
-        .expect("Failed to open port");
-    port
+        .expect("Failed to open port")
 }
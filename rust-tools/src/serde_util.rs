@@ -0,0 +1,21 @@
+use jiff::civil::Weekday;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/* jiff does not serialize weekdays, we use their ISO 8601 number (Monday is
+ * 1), which is also how the time codes send them. For `Option<Weekday>`:
+ *
+ *  #[serde(with = "crate::serde_util::option_weekday")]
+ */
+pub mod option_weekday {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(weekday: &Option<Weekday>, serializer: S) -> Result<S::Ok, S::Error> {
+        weekday.map(|weekday| weekday.to_monday_one_offset()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Weekday>, D::Error> {
+        Option::<i8>::deserialize(deserializer)?
+            .map(|number| Weekday::from_monday_one_offset(number).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
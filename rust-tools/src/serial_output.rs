@@ -0,0 +1,97 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serialport::{ClearBuffer, SerialPort, SerialPortBuilder, TTYPort};
+
+/* A serial port we write telegrams to every second, for equipment that
+ * expects a clock on a serial line: either a real tty, or a new
+ * pseudo-terminal that programs on this machine open instead.
+ *
+ * Pseudo-terminals get a new name every time, so they can be linked to a
+ * fixed path (e.g. /dev/refclock-0 for ntpd), removed again when dropped.
+ */
+
+// Don't stall decoding if nobody reads
+const WRITE_TIMEOUT: Duration = Duration::from_millis(20);
+
+pub struct SerialOutput {
+    port: TTYPort,
+    // Kept open, so the pty stays usable while no reader is attached
+    pty_slave: Option<TTYPort>,
+    path: String,
+    link: Option<PathBuf>
+}
+
+impl SerialOutput {
+    // A new pseudo-terminal, readers open `path()`
+    pub fn pty() -> io::Result<SerialOutput> {
+        let (mut master, slave) = TTYPort::pair()?;
+        master.set_timeout(WRITE_TIMEOUT)?;
+        let path = slave.name().unwrap_or_default();
+        Ok(SerialOutput { port: master, pty_slave: Some(slave), path, link: None })
+    }
+
+    // Baud rate and framing as set up by the caller
+    pub fn open(port: SerialPortBuilder) -> io::Result<SerialOutput> {
+        let port = port.timeout(WRITE_TIMEOUT).open_native()?;
+        let path = port.name().unwrap_or_default();
+        Ok(SerialOutput { port, pty_slave: None, path, link: None })
+    }
+
+    // Replaces an existing link
+    pub fn link_to(&mut self, link: impl AsRef<Path>) -> io::Result<()> {
+        let link = link.as_ref();
+        if link.symlink_metadata().is_ok_and(|meta| meta.file_type().is_symlink()) {
+            std::fs::remove_file(link)?;
+        }
+        std::os::unix::fs::symlink(&self.path, link)?;
+        self.link = Some(link.to_path_buf());
+        Ok(())
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn write_second(&mut self, telegram: &[u8]) -> io::Result<()> {
+        // Nobody read the last second, a late reader should not get stale times
+        if let Some(ref slave) = self.pty_slave {
+            slave.clear(ClearBuffer::Input)?;
+        }
+        self.port.write_all(telegram)
+    }
+}
+
+impl Drop for SerialOutput {
+    fn drop(&mut self) {
+        if let Some(ref link) = self.link {
+            let _ = std::fs::remove_file(link);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use super::*;
+
+    #[test]
+    fn test_pty() {
+        let mut output = SerialOutput::pty().expect("Could not create pty");
+        let link = std::env::temp_dir().join(format!("rust-tools-pty-{}", std::process::id()));
+        output.link_to(&link).expect("Could not link pty");
+        let mut reader = serialport::new(link.to_string_lossy(), 9600)
+            .timeout(Duration::from_secs(1))
+            .open_native()
+            .expect("Could not open pty");
+        output.write_second(b"stale").unwrap();
+        output.write_second(b"second\r\n").unwrap();
+
+        let mut buf = [0u8; 8];
+        reader.read_exact(&mut buf).expect("Could not read");
+        assert_eq!(&buf, b"second\r\n");
+        drop(output);
+        assert!(link.symlink_metadata().is_err());
+    }
+}
@@ -0,0 +1,301 @@
+use std::fmt::{self};
+
+use ringbuffer::{RingBuffer,AllocRingBuffer};
+
+use crate::pulse_decoder::{Pulse, PulseTiming};
+use crate::time_code::{self, DecoderOptions, TimeCodeDecoder};
+
+/* Station detection: pulses are measured at 25 baud (40ms per bit, up to
+ * 360ms), which is enough to tell the pulse alphabets apart
+ *
+ *  Station     Pulses                              Marker
+ *  DCF77       100ms, 200ms                        no pulse at second 59
+ *  MSF         100ms, 200ms, 300ms, double         500ms at second 0
+ *  WWVB        200ms, 500ms, 800ms                 800ms at 0, 9, 19, ... 59
+ *  JJY         200ms, 500ms, 800ms                 800ms at 0, 9, 19, ... 59
+ *
+ * WWVB and JJY share their pulses and markers, they only differ in the
+ * frame contents. Their signature gives both as candidates, and
+ * `CandidateDecoders` decodes until only one of them makes sense.
+ */
+pub const DETECT_TIMING: PulseTiming = PulseTiming { bit_ms: 40, period_ms: 1000 };
+
+// Three minutes of pulses, at least two are needed
+const WINDOW: usize = 180;
+const MIN_PULSES: usize = 120;
+
+// Allowed share of pulses outside the alphabet, or breaking the marker pattern
+const TOLERANCE: f32 = 0.05;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PulseClass {
+    Missing,
+    Invalid,
+    Short,      // up to 150ms
+    Medium,     // up to 250ms
+    Long,       // up to 360ms
+    Saturated,
+    Double
+}
+
+impl PulseClass {
+    pub fn classify(pulse: u8) -> PulseClass {
+        match DETECT_TIMING.measure(pulse) {
+            Pulse::None => Self::Missing,
+            Pulse::Invalid => Self::Invalid,
+            Pulse::Single { saturated: true, .. } => Self::Saturated,
+            Pulse::Single { width_ms, .. } if width_ms <= 150 => Self::Short,
+            Pulse::Single { width_ms, .. } if width_ms <= 250 => Self::Medium,
+            Pulse::Single { .. } => Self::Long,
+            Pulse::Double { .. } => Self::Double,
+        }
+    }
+}
+
+/* What a station looks like at the detection timing. If `exclusive`, the
+    marker class is not used for anything but markers. `names` are the
+    stations that look like this.
+ */
+struct Signature {
+    names: &'static [&'static str],
+    alphabet: &'static [PulseClass],
+    marker: PulseClass,
+    markers: &'static [usize],
+    exclusive: bool
+}
+
+const TEN_SECOND_MARKERS: &[usize] = &[0, 9, 19, 29, 39, 49, 59];
+
+const SIGNATURES: [Signature; 3] = [
+    Signature {
+        names: &["dcf77"],
+        alphabet: &[PulseClass::Short, PulseClass::Medium, PulseClass::Missing],
+        marker: PulseClass::Missing,
+        markers: &[59],
+        exclusive: true
+    },
+    Signature {
+        names: &["msf"],
+        alphabet: &[PulseClass::Short, PulseClass::Medium, PulseClass::Long,
+            PulseClass::Double, PulseClass::Saturated],
+        marker: PulseClass::Saturated,
+        markers: &[0],
+        exclusive: true
+    },
+    Signature {
+        names: &["wwvb", "jjy"],
+        alphabet: &[PulseClass::Medium, PulseClass::Saturated],
+        marker: PulseClass::Saturated,
+        markers: TEN_SECOND_MARKERS,
+        exclusive: false
+    },
+];
+
+impl Signature {
+    // Share of pulses breaking the marker pattern, for the best offset
+    fn marker_mismatch(&self, pulses: &[PulseClass]) -> f32 {
+        (0..60).map(|offset| {
+            let mut checked = 0;
+            let mut broken = 0;
+            for (n, class) in pulses.iter().enumerate() {
+                let is_marker = self.markers.contains(&((n + offset) % 60));
+                if is_marker || self.exclusive {
+                    checked += 1;
+                    if is_marker != (*class == self.marker) {
+                        broken += 1;
+                    }
+                }
+            }
+            broken as f32 / checked as f32
+        })
+        .fold(1.0, f32::min)
+    }
+
+    fn alphabet_mismatch(&self, pulses: &[PulseClass]) -> f32 {
+        let outside = pulses.iter().filter(|class| !self.alphabet.contains(class)).count();
+        outside as f32 / pulses.len() as f32
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Detection {
+    // More than one if the stations look the same, see `CandidateDecoders`
+    pub names: &'static [&'static str],
+    pub seconds: usize,
+    // Share of pulses that fit the station's alphabet and marker pattern
+    pub confidence: f32
+}
+
+impl fmt::Display for Detection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({:.0}% of {} seconds fit)", self.names.join(" or "), self.confidence * 100.0, self.seconds)
+    }
+}
+
+pub struct StationDetector {
+    pulses: AllocRingBuffer<PulseClass>
+}
+
+impl StationDetector {
+    pub fn new() -> StationDetector {
+        StationDetector {
+            pulses: AllocRingBuffer::new(WINDOW)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pulses.len()
+    }
+
+    // Add an (inverted) pulse byte measured at `DETECT_TIMING`
+    pub fn add_pulse(&mut self, pulse: u8) -> PulseClass {
+        let class = PulseClass::classify(pulse);
+        self.pulses.enqueue(class);
+        class
+    }
+
+    pub fn detect(&self) -> Option<Detection> {
+        if self.pulses.len() < MIN_PULSES {
+            return None
+        }
+        let pulses = self.pulses.to_vec();
+        SIGNATURES.iter()
+            .map(|signature| {
+                let mismatch = signature.alphabet_mismatch(&pulses)
+                    .max(signature.marker_mismatch(&pulses));
+                (signature, mismatch)
+            })
+            .find(|(_, mismatch)| *mismatch <= TOLERANCE)
+            .map(|(signature, mismatch)| Detection {
+                names: signature.names,
+                seconds: pulses.len(),
+                confidence: 1.0 - mismatch
+            })
+    }
+}
+
+/* Decoders for stations sharing a signature, fed the same pulses until
+    one of them decodes a frame the others can't. They have to share the
+    pulse timing.
+ */
+pub struct CandidateDecoders {
+    decoders: Vec<Box<dyn TimeCodeDecoder>>
+}
+
+impl CandidateDecoders {
+    pub fn new(names: &[&str], options: &DecoderOptions) -> Option<CandidateDecoders> {
+        let decoders = names.iter()
+            .map(|name| time_code::decoder_by_name(name, options))
+            .collect::<Option<Vec<_>>>()?;
+        let timing = decoders.first()?.pulse_timing();
+        if decoders.iter().any(|decoder| decoder.pulse_timing() != timing) {
+            return None
+        }
+        Some(CandidateDecoders { decoders })
+    }
+
+    pub fn pulse_timing(&self) -> PulseTiming {
+        self.decoders[0].pulse_timing()
+    }
+
+    // The decoder of the station, once it is the only one that decodes
+    pub fn add_pulse(&mut self, pulse: u8) -> Option<Box<dyn TimeCodeDecoder>> {
+        for decoder in self.decoders.iter_mut() {
+            decoder.feed_pulse(pulse);
+        }
+        let decoded: Vec<bool> = self.decoders.iter()
+            .map(|decoder| decoder.is_synced() && decoder.decode().is_ok())
+            .collect();
+        match decoded.iter().filter(|ok| **ok).count() {
+            1 => {
+                let n = decoded.iter().position(|ok| *ok)?;
+                Some(self.decoders.swap_remove(n))
+            }
+            _ => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHORT: u8 = 0b1;
+    const MEDIUM: u8 = 0b1111;
+    const LONG: u8 = 0b111_1111;
+    const SATURATED: u8 = 0xFF;
+    const DOUBLE: u8 = 0b0111_0001;
+
+    // Three minutes of made up data, starting at second 17
+    fn signal(second: impl Fn(usize, usize) -> u8) -> StationDetector {
+        let mut detector = StationDetector::new();
+        for n in 17..17 + 180 {
+            detector.add_pulse(second(n % 60, n * 7 % 11));
+        }
+        detector
+    }
+
+    #[test]
+    fn test_detect() {
+        let dcf77 = signal(|s, data| match s {
+            59 => 0,
+            _ => if data < 5 { SHORT } else { MEDIUM }
+        });
+        let msf = signal(|s, data| match s {
+            0 => SATURATED,
+            _ => [SHORT, MEDIUM, LONG, DOUBLE][data % 4]
+        });
+        let wwvb = signal(|s, data| match s {
+            0 | 9 | 19 | 29 | 39 | 49 | 59 => SATURATED,
+            _ => if data < 3 { SATURATED } else { MEDIUM }
+        });
+        for (detector, names) in [(dcf77, ["dcf77"].as_slice()), (msf, &["msf"]), (wwvb, &["wwvb", "jjy"])] {
+            let detection = detector.detect().expect("No station detected");
+            assert_eq!(detection.names, names);
+            assert_eq!(detection.confidence, 1.0);
+        }
+    }
+
+    #[test]
+    fn test_candidate_decoders() {
+        const ZERO: u8 = 0b11;
+        const ONE: u8 = 0b1_1111;
+        const MARKER: u8 = 0b1111_1111;
+        // WWVB 2000-01-01 (day 1) 00:00 UTC, DST in effect: JJY has no DST bits
+        let wwvb: Vec<u8> = (0..60).map(|n| match n {
+            0 | 9 | 19 | 29 | 39 | 49 | 59 => MARKER,
+            33 | 37 | 57 | 58 => ONE,
+            _ => ZERO
+        }).collect();
+        // JJY 2000-01-01 (day 1, Saturday) 00:00 JST: no DUT1 sign, and the weekday is no WWVB year digit
+        let jjy: Vec<u8> = (0..60).map(|n| match n {
+            0 | 9 | 19 | 29 | 39 | 49 | 59 => MARKER,
+            33 | 50 | 51 => ONE,
+            _ => ZERO
+        }).collect();
+        for (signal, name) in [(wwvb, "wwvb"), (jjy, "jjy")] {
+            let mut candidates = CandidateDecoders::new(&["wwvb", "jjy"], &DecoderOptions::default())
+                .expect("Candidates should share the timing");
+            let decoder = signal.iter().chain(signal.iter())
+                .find_map(|pulse| candidates.add_pulse(*pulse))
+                .expect("No candidate decoded");
+            assert_eq!(decoder.name(), name);
+        }
+        assert!(CandidateDecoders::new(&["dcf77", "wwvb"], &DecoderOptions::default()).is_none());
+    }
+
+    #[test]
+    fn test_detect_noise() {
+        let mut detector = StationDetector::new();
+        for n in 0..100 {
+            detector.add_pulse(if n % 60 == 59 { 0 } else { SHORT });
+        }
+        assert_eq!(detector.detect(), None);
+        for n in 100..180 {
+            detector.add_pulse([SHORT, LONG, 0][n % 3]);
+        }
+        assert_eq!(detector.detect(), None);
+    }
+}
@@ -0,0 +1,425 @@
+use std::any::Any;
+use std::fmt::{self};
+use std::ops::Range;
+use std::sync::Arc;
+
+use jiff::{SignedDuration, Timestamp, Zoned};
+
+use crate::bit_frame::FrameError;
+use crate::bitrep::*;
+use crate::century::{CenturyInference, CenturyPolicy};
+use crate::frame_view::{self, FieldSpan, Highlight, RenderStyle, SpanKind};
+use crate::pulse_decoder::{PulseTiming, WidthClasses};
+use crate::{dcf77_decoder, irig_decoder, jjy_decoder, msf_decoder, wwvb_decoder};
+
+/* A time code format, as seen by the CLI and the displays: pulses are
+ * classified into per-second symbols, buffered for one frame, and the frame
+ * decodes to a time once the sync marker is in place.
+ *
+ * Each station's decoder module implements this for its `Decoder`, the
+ * station specific API (frame details, partial decoding, ...) stays there.
+ */
+pub trait TimeCodeDecoder: fmt::Display {
+    // Short name, as chosen on the command line
+    fn name(&self) -> &'static str;
+
+    // UART timing needed to measure the pulses of this format
+    fn pulse_timing(&self) -> PulseTiming;
+
+    // All `Error::kind`s the format can fail with
+    fn failure_kinds(&self) -> Vec<&'static str>;
+
+    // Widest pulse (ms) of each class and its symbol, see `WidthClasses`
+    fn pulse_thresholds(&self) -> Vec<(u32, char)>;
+
+    fn frame_len(&self) -> usize;
+
+    // All symbols a second can decode to, as printed
+    fn alphabet(&self) -> &'static str;
+
+    fn layout(&self) -> &'static [FieldSpan];
+
+    // Classify and buffer one (inverted) pulse byte, returning its symbol
+    fn feed_pulse(&mut self, pulse: u8) -> char;
+
+    // Buffer a symbol as printed (one of `alphabet`), false for any other character
+    fn feed_symbol(&mut self, symbol: char) -> bool;
+
+    // Positions in the frame that carry no pulse, like DCF77's minute marker
+    fn gaps(&self) -> &'static [usize] {
+        &[]
+    }
+
+    // Whether the buffer holds exactly one frame, according to the sync markers
+    fn is_synced(&self) -> bool;
+
+    // The bit carried by each buffered second, markers as skipped
+    fn frame_bits(&self) -> Vec<Bit>;
+
+    fn decode(&self) -> Result<DecodedFrame>;
+
+    // Error message with any station specific details
+    fn report(&self, error: &Error) -> String {
+        error.to_string()
+    }
+
+    // Fields that could be decoded despite the error, if supported
+    fn partial(&self) -> Option<String> {
+        None
+    }
+
+    /* Annotated view of the current frame, see `frame_view`. Unknown bits
+        are highlighted. Until the frame is in sync, the symbols are printed
+        as they are.
+     */
+    fn render(&self, style: RenderStyle) -> String {
+        if !self.is_synced() {
+            return format!("{:>width$}\n", self.to_string(), width = self.frame_len());
+        }
+        let bits = self.frame_bits();
+        let mut highlights: Vec<Option<Highlight>> = vec![None; bits.len()];
+        for span in self.layout().iter().filter(|span| span.kind != SpanKind::Marker) {
+            for n in span.bits.clone() {
+                if bits[n].to_bit().is_none() {
+                    highlights[n] = Some(Highlight::Unknown);
+                }
+            }
+        }
+        frame_view::render(&bits, self.layout(), &highlights, style)
+    }
+}
+
+/* A successfully decoded frame. DCF77 and MSF announce the minute starting
+    after the frame, WWVB, JJY and IRIG-B the minute (or second) the frame
+    was sent in, so `next_frame` gives the start of the frame following the
+    last symbol of the frame for either.
+ */
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecodedFrame {
+    pub time: Zoned,
+    pub next_frame: Zoned,
+    pub century: CenturyInference,
+    // Announced for the end of the hour (DCF77), month (WWVB) or day (JJY)
+    pub leap_second: LeapSecond,
+    // Whether `time` is summer time, and a change to or from it is announced
+    pub summer_time: bool,
+    pub summer_time_change: bool
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LeapSecond {
+    None,
+    Inserted,
+    Deleted
+}
+
+/* A second (or minute) mark for time servers: the time it stands for, and
+    the system time its pulse started at.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct RefclockSample {
+    pub clock: Timestamp,
+    pub receive: Timestamp,
+    pub leap_second: LeapSecond
+}
+
+impl RefclockSample {
+    // How far the system clock is behind
+    pub fn offset(&self) -> SignedDuration {
+        self.clock.duration_since(self.receive)
+    }
+}
+
+/* Lock onto the frames: once a frame decodes, the symbol after it is the
+    start of `next_frame`, and every symbol after that one pulse period
+    later. Frames failing to decode don't lose the lock, we keep counting,
+    but a missing pulse does (except in the format's gaps).
+ */
+#[derive(Clone, Debug)]
+pub struct FrameLock {
+    period: SignedDuration,
+    frame_len: usize,
+    gaps: &'static [usize],
+    // Start of the frame being received, and the position of the next symbol in it
+    next: Option<(Zoned, usize)>,
+    last: Option<Zoned>
+}
+
+impl FrameLock {
+    pub fn new(decoder: &dyn TimeCodeDecoder) -> FrameLock {
+        FrameLock {
+            period: SignedDuration::from_millis(i64::from(decoder.pulse_timing().period_ms)),
+            frame_len: decoder.frame_len(),
+            gaps: decoder.gaps(),
+            next: None,
+            last: None
+        }
+    }
+
+    /* After every symbol, with the frame if it completed one: the time the
+        symbol stands for, while locked
+     */
+    pub fn update(&mut self, symbol: char, decoded: Option<&DecodedFrame>) -> Option<Zoned> {
+        self.last = match self.next.take() {
+            Some((start, n)) if symbol != '_' || self.gaps.contains(&(n % self.frame_len)) => {
+                let time = start.checked_add(self.period * n as i32).ok();
+                self.next = time.is_some().then_some((start, n + 1));
+                time
+            }
+            _ => None
+        };
+        if let Some(frame) = decoded {
+            if self.last.is_none() {
+                self.last = frame.next_frame.checked_sub(self.period).ok();
+            }
+            self.next = Some((frame.next_frame.clone(), 0));
+        }
+        self.last.clone()
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.next.is_some()
+    }
+
+    // Time of the last symbol, if it was locked
+    pub fn last(&self) -> Option<&Zoned> {
+        self.last.as_ref()
+    }
+}
+
+/* A station's own error type, behind `Error`: each decoder module has one,
+    and the CLI only needs to tell their kinds apart.
+ */
+pub trait StationError: Any + std::error::Error + Send + Sync {
+    // The `DecodingFailure` variant without its details (e.g. "ParityError"), or "JiffError"
+    fn kind(&self) -> &'static str;
+
+    // Not (yet) in sync with the frame, no need to report this
+    fn is_not_sync(&self) -> bool;
+}
+
+#[derive(Clone, Debug)]
+pub struct Error(Arc<dyn StationError>);
+
+impl Error {
+    pub fn kind(&self) -> &'static str {
+        self.0.kind()
+    }
+
+    pub fn is_not_sync(&self) -> bool {
+        self.0.is_not_sync()
+    }
+
+    // The station's error, for its `TimeCodeDecoder::report`
+    pub fn downcast_ref<E: StationError>(&self) -> Option<&E> {
+        let error: &dyn Any = &*self.0;
+        error.downcast_ref()
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<E: StationError> From<E> for Error {
+    fn from(value: E) -> Self {
+        Error(Arc::new(value))
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/* The `DecodingFailure` of a station's decoder module, which is wrapped in
+    `DecodeError` as the module's `Error`. Fields are decoded by the frame's
+    generated decoders (see `bit_frame`) and `decode_field`.
+ */
+pub trait StationFailure: Copy + fmt::Debug + fmt::Display + Send + Sync + 'static {
+    // Names the field in failures like `OutOfRange`
+    type Field: Copy;
+    // The frame's parity groups, `Infallible` without any
+    type Parity;
+
+    // The variant without its details, e.g. for metrics labels
+    fn kind(&self) -> &'static str;
+
+    fn is_not_sync(&self) -> bool;
+
+    fn field_failure(field: Self::Field, error: FrameError<Self::Parity>) -> Self;
+
+    fn out_of_range(field: Self::Field, value: i16) -> Self;
+}
+
+#[derive(Clone, Debug)]
+pub enum DecodeError<F> {
+    DecodingError(F),
+    JiffError(jiff::Error)
+}
+
+impl<F: StationFailure> std::error::Error for DecodeError<F> {}
+
+impl<F: fmt::Display> fmt::Display for DecodeError<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DecodingError(e) => write!(f, "{}", e),
+            Self::JiffError(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl<F: StationFailure> From<F> for DecodeError<F> {
+    fn from(value: F) -> Self {
+        DecodeError::DecodingError(value)
+    }
+}
+
+impl<F> From<jiff::Error> for DecodeError<F> {
+    fn from(value: jiff::Error) -> Self {
+        DecodeError::JiffError(value)
+    }
+}
+
+impl<F: StationFailure> StationError for DecodeError<F> {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::DecodingError(e) => e.kind(),
+            Self::JiffError(_) => "JiffError",
+        }
+    }
+
+    fn is_not_sync(&self) -> bool {
+        matches!(self, Self::DecodingError(e) if e.is_not_sync())
+    }
+}
+
+// A field from the frame's generated decoder
+pub fn decode_value<F: StationFailure, T>(field: F::Field, decoded: std::result::Result<T, FrameError<F::Parity>>) -> std::result::Result<T, DecodeError<F>> {
+    decoded.map_err(|e| From::from(F::field_failure(field, e)))
+}
+
+// Same for numbers, checked against the values they can take
+pub fn decode_field<F: StationFailure>(field: F::Field, decoded: std::result::Result<i16, FrameError<F::Parity>>, valid: Range<i16>) -> std::result::Result<i16, DecodeError<F>> {
+    let value = decode_value(field, decoded)?;
+    if !valid.contains(&value) {
+        return Err(From::from(F::out_of_range(field, value)))
+    }
+    Ok(value)
+}
+
+pub const FORMAT_NAMES: [&str; 5] = ["dcf77", "msf", "wwvb", "jjy", "irig-b"];
+
+// Settings from the command line, for whichever decoder is chosen
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DecoderOptions {
+    // Not used by IRIG-B, which sends no year at all
+    pub century: CenturyPolicy,
+    // Only for IRIG-B
+    pub year: Option<i16>
+}
+
+pub fn decoder_by_name(name: &str, options: &DecoderOptions) -> Option<Box<dyn TimeCodeDecoder>> {
+    match name {
+        "dcf77" => Some(Box::new(dcf77_decoder::Decoder::new().with_century(options.century))),
+        "msf"   => Some(Box::new(msf_decoder::Decoder::new().with_century(options.century))),
+        "wwvb"  => Some(Box::new(wwvb_decoder::Decoder::new().with_century(options.century))),
+        "jjy"   => Some(Box::new(jjy_decoder::Decoder::new().with_century(options.century))),
+        "irig-b" => Some(Box::new(match options.year {
+            Some(year) => irig_decoder::Decoder::new().with_year(year),
+            None => irig_decoder::Decoder::new()
+        })),
+        _ => None
+    }
+}
+
+// The printed form of a symbol, for `TimeCodeDecoder::feed_pulse`
+pub fn symbol_char(symbol: impl fmt::Display) -> char {
+    symbol.to_string().chars().next().unwrap_or('?')
+}
+
+// For `TimeCodeDecoder::failure_kinds`: the station's failures and errors from jiff
+pub fn failure_kinds(kinds: &[&'static str]) -> Vec<&'static str> {
+    kinds.iter().copied().chain(["JiffError"]).collect()
+}
+
+// For `TimeCodeDecoder::pulse_thresholds`
+pub fn width_thresholds<S: Copy + fmt::Display>(classes: &WidthClasses<S>) -> Vec<(u32, char)> {
+    classes.classes.iter().map(|(limit, symbol)| (*limit, symbol_char(symbol))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decoder_by_name() {
+        for name in FORMAT_NAMES {
+            let decoder = decoder_by_name(name, &DecoderOptions::default()).expect("Unknown format");
+            assert_eq!(decoder.name(), name);
+            assert_eq!(decoder.layout().last().map(|span| span.bits.end), Some(decoder.frame_len()));
+            assert!(!decoder.pulse_thresholds().is_empty());
+            assert!(decoder.failure_kinds().contains(&"NotSync"));
+        }
+        assert!(decoder_by_name("loran", &DecoderOptions::default()).is_none());
+    }
+
+    // WWVB: 2000-01-01 (day 1) 00:00 UTC, DUT1 -0.0
+    const ZERO: u8 = 0b1;
+    const ONE: u8 = 0b1111;
+    const MARKER: u8 = 0b111_1111;
+
+    fn wwvb_pulse(n: usize) -> u8 {
+        match n {
+            0 | 9 | 19 | 29 | 39 | 49 | 59 => MARKER,
+            33 | 37 => ONE,
+            _ => ZERO
+        }
+    }
+
+    #[test]
+    fn test_generic_decode() {
+        let mut decoder = decoder_by_name("wwvb", &DecoderOptions::default()).unwrap();
+        for n in 0..60 {
+            decoder.feed_pulse(wwvb_pulse(n));
+            assert_eq!(decoder.is_synced(), n == 59);
+        }
+        let frame = decoder.decode().expect("Could not decode wwvb time");
+        assert_eq!(frame.time.to_string(), "2000-01-01T00:00:00+00:00[UTC]");
+        assert_eq!(frame.next_frame.to_string(), "2000-01-01T00:01:00+00:00[UTC]");
+        assert!(decoder.render(RenderStyle::Plain).starts_with("M 10m _ min  P1"));
+
+        decoder.feed_pulse(ZERO);
+        let error = decoder.decode().unwrap_err();
+        assert!(error.is_not_sync());
+        assert_eq!(error.kind(), "NotSync");
+        let parity = Error::from(dcf77_decoder::Error::DecodingError(
+            dcf77_decoder::DecodingFailure::ParityError(dcf77_decoder::ParityBitName::Date)));
+        assert_eq!(parity.kind(), "ParityError");
+        assert!(parity.downcast_ref::<dcf77_decoder::Error>().is_some());
+        assert!(error.downcast_ref::<dcf77_decoder::Error>().is_none());
+    }
+
+    #[test]
+    fn test_frame_lock() {
+        let mut decoder = decoder_by_name("wwvb", &DecoderOptions::default()).unwrap();
+        let mut lock = FrameLock::new(decoder.as_ref());
+        let mut feed = |decoder: &mut Box<dyn TimeCodeDecoder>, pulse: u8| {
+            let symbol = decoder.feed_pulse(pulse);
+            let frame = decoder.is_synced().then(|| decoder.decode().ok()).flatten();
+            lock.update(symbol, frame.as_ref()).map(|time| time.to_string())
+        };
+        for n in 0..59 {
+            assert_eq!(feed(&mut decoder, wwvb_pulse(n)), None);
+        }
+        assert_eq!(feed(&mut decoder, wwvb_pulse(59)).as_deref(), Some("2000-01-01T00:00:59+00:00[UTC]"));
+        assert_eq!(feed(&mut decoder, MARKER).as_deref(), Some("2000-01-01T00:01:00+00:00[UTC]"));
+        // Bad pulses keep the count, a missing one loses the lock
+        assert_eq!(feed(&mut decoder, 0b1_0101).as_deref(), Some("2000-01-01T00:01:01+00:00[UTC]"));
+        assert_eq!(feed(&mut decoder, 0).as_deref(), None);
+        assert!(!lock.is_locked());
+    }
+}
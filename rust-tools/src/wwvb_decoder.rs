@@ -0,0 +1,548 @@
+use std::convert::Infallible;
+use std::fmt::{self};
+use std::ops::Range;
+
+use jiff::{SignedDuration, Zoned, civil::Date, tz};
+
+use crate::bit_frame::{BitEnum, BitFrame, BitRange, FrameError};
+use crate::bitrep::*;
+use crate::century::{CenturyInference, CenturyPolicy};
+use crate::frame_view::FieldSpan;
+use crate::marker_sync::{MarkerLayout, MarkerSymbol, MarkerSync, marker_bits};
+use crate::pulse_decoder::{Pulse, PulseTiming, WidthClasses};
+use crate::time_code::{self, DecodeError, DecodedFrame, LeapSecond, StationFailure, TimeCodeDecoder, decode_field, decode_value, symbol_char};
+
+/* WWVB (Fort Collins, US, 60 kHz), see e.g. https://en.wikipedia.org/wiki/WWVB
+ *
+ * The carrier is reduced at the start of every second, for
+ *      200ms       0
+ *      500ms       1
+ *      800ms       marker (seconds 0, 9, 19, ... 59)
+ * Unlike DCF77 and MSF, the code gives the (UTC) time of the minute it is
+ * transmitted in, starting at the frame reference marker at second 0.
+ *
+ * Second       Contents
+ * 0            frame reference marker
+ * 1  - 8       BCD (msb first) Minutes: 40 20 10 0 8 4 2 1
+ * 9            P1
+ * 12 - 18      BCD (msb first) Hours: 20 10 0 8 4 2 1
+ * 19           P2
+ * 22 - 28      BCD (msb first) Day of year: 200 100 0 80 40 20 10
+ * 29           P3
+ * 30 - 33      BCD (msb first) Day of year: 8 4 2 1
+ * 36 - 38      DUT1 sign: 101 = +, 010 = -
+ * 39           P4
+ * 40 - 43      BCD (msb first) DUT1 in 0.1s
+ * 45 - 48      BCD (msb first) Years: 80 40 20 10
+ * 49           P5
+ * 50 - 53      BCD (msb first) Years: 8 4 2 1
+ * 55           leap year indicator
+ * 56           leap second at the end of the month
+ * 57 - 58      DST status: 00 standard time, 10 DST begins today,
+ *              11 DST in effect, 01 DST ends today
+ * 59           P0
+ * All other seconds are always 0.
+ *
+ * Pulses up to 800ms need 10 baud (100ms per bit, saturating at 900ms).
+ */
+pub const PULSE_TIMING: PulseTiming = PulseTiming { bit_ms: 100, period_ms: 1000 };
+
+pub const LAYOUT: MarkerLayout = MarkerLayout {
+    len: 60,
+    markers: &[0, 9, 19, 29, 39, 49, 59]
+};
+
+// The markers and unused seconds are taken out of the fields (`skip`)
+#[allow(clippy::duplicated_attributes)]
+#[derive(Clone, Debug, PartialEq, BitFrame)]
+#[frame(len = 60)]
+#[fixed(name = "frame_reference", bits = 0, label = "M", skipped)]
+#[fixed(name = "p1", bits = 9, label = "P1", skipped)]
+#[fixed(name = "p2", bits = 19, label = "P2", skipped)]
+#[fixed(name = "p3", bits = 29, label = "P3", skipped)]
+#[fixed(name = "p4", bits = 39, label = "P4", skipped)]
+#[fixed(name = "p5", bits = 49, label = "P5", skipped)]
+#[fixed(name = "p0", bits = 59, label = "P0", skipped)]
+#[fixed(name = "unused", bits = [4, 10..12, 14, 20..22, 24, 34..36, 44, 54], label = "_")]
+pub struct WwvbFields {
+    #[field(bits = 1..9, skip = [4], bcd = [3, 4], msb, labels = ["10m", "min"])]
+    pub minute: i16,
+    #[field(bits = 12..19, skip = [14], bcd = [2, 4], msb, labels = ["10h", "hour"])]
+    pub hour: i16,
+    #[field(bits = 22..34, skip = [24, 29], bcd = [2, 4, 4], msb, labels = ["100d", "10d", "day"])]
+    pub day_of_year: i16,
+    #[field(bits = 36..39, enumeration, msb, label = "sgn")]
+    pub dut1_sign: Dut1Sign,
+    #[field(bits = 40..44, bcd = [4], msb, labels = ["dut1"])]
+    pub dut1: i16,
+    #[field(bits = 45..54, skip = [49], bcd = [4, 4], msb, labels = ["10y", "year"])]
+    pub year: i16,
+    #[field(bits = 55, label = "LY")]
+    pub leap_year: bool,
+    #[field(bits = 56, label = "LS")]
+    pub leap_second_warning: bool,
+    #[field(bits = 57..59, enumeration, msb, label = "DST")]
+    pub dst: DstStatus
+}
+
+const PULSE_CLASSES: WidthClasses<MarkerSymbol> = WidthClasses {
+    classes: &[
+        (350, MarkerSymbol::Zero),
+        (650, MarkerSymbol::One),
+    ],
+    longer: MarkerSymbol::Marker
+};
+
+pub fn decode_pulse(pulse: u8) -> MarkerSymbol {
+    match PULSE_TIMING.measure(pulse) {
+        Pulse::None => MarkerSymbol::Skipped,
+        Pulse::Single { width_ms, .. } => PULSE_CLASSES.classify(width_ms),
+        _ => MarkerSymbol::Unknown
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    Minute,
+    Hour,
+    DayOfYear,
+    Dut1Sign,
+    Dut1,
+    Year,
+    LeapYear,
+    LeapSecondWarning,
+    DstStatus
+}
+
+impl Field {
+    pub fn bit_range(&self) -> Range<usize> {
+        match *self {
+            Self::Minute            => WwvbFields::MINUTE_BITS,
+            Self::Hour              => WwvbFields::HOUR_BITS,
+            Self::DayOfYear         => WwvbFields::DAY_OF_YEAR_BITS,
+            Self::Dut1Sign          => WwvbFields::DUT1_SIGN_BITS,
+            Self::Dut1              => WwvbFields::DUT1_BITS,
+            Self::Year              => WwvbFields::YEAR_BITS,
+            Self::LeapYear          => WwvbFields::LEAP_YEAR_BITS,
+            Self::LeapSecondWarning => WwvbFields::LEAP_SECOND_WARNING_BITS,
+            Self::DstStatus         => WwvbFields::DST_BITS,
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", match *self {
+            Self::Minute            => "minute",
+            Self::Hour              => "hour",
+            Self::DayOfYear         => "day of year",
+            Self::Dut1Sign          => "DUT1 sign",
+            Self::Dut1              => "DUT1",
+            Self::Year              => "year",
+            Self::LeapYear          => "leap year",
+            Self::LeapSecondWarning => "leap second warning",
+            Self::DstStatus         => "DST status",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dut1Sign {
+    Positive,
+    Negative
+}
+
+impl BitEnum for Dut1Sign {
+    fn from_bit_value(value: u32) -> Option<Self> {
+        match value {
+            0b101 => Some(Self::Positive),
+            0b010 => Some(Self::Negative),
+            _ => None
+        }
+    }
+
+    fn to_bit_value(&self) -> u32 {
+        match *self {
+            Self::Positive => 0b101,
+            Self::Negative => 0b010,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DstStatus {
+    Standard,
+    BeginsToday,
+    InEffect,
+    EndsToday
+}
+
+impl BitEnum for DstStatus {
+    fn from_bit_value(value: u32) -> Option<Self> {
+        match value {
+            0b00 => Some(Self::Standard),
+            0b10 => Some(Self::BeginsToday),
+            0b11 => Some(Self::InEffect),
+            0b01 => Some(Self::EndsToday),
+            _ => None
+        }
+    }
+
+    fn to_bit_value(&self) -> u32 {
+        match *self {
+            Self::Standard      => 0b00,
+            Self::BeginsToday   => 0b10,
+            Self::InEffect      => 0b11,
+            Self::EndsToday     => 0b01,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodingFailure {
+    NotEnoughBits,
+    NotSync,    /* Markers are not where they belong */
+    MissingBit(usize),
+    UnusedBitSet(usize),
+    InvalidDut1Sign,
+    InvalidDigit(Field, u8),
+    OutOfRange(Field, i16)
+}
+
+impl DecodingFailure {
+    pub const KINDS: [&'static str; 7] = ["NotEnoughBits", "NotSync", "MissingBit", "UnusedBitSet", "InvalidDut1Sign", "InvalidDigit", "OutOfRange"];
+}
+
+impl StationFailure for DecodingFailure {
+    type Field = Field;
+    type Parity = Infallible;
+
+    fn kind(&self) -> &'static str {
+        match *self {
+            Self::NotEnoughBits    => "NotEnoughBits",
+            Self::NotSync          => "NotSync",
+            Self::MissingBit(_)    => "MissingBit",
+            Self::UnusedBitSet(_)  => "UnusedBitSet",
+            Self::InvalidDut1Sign  => "InvalidDut1Sign",
+            Self::InvalidDigit(..) => "InvalidDigit",
+            Self::OutOfRange(..)   => "OutOfRange",
+        }
+    }
+
+    fn is_not_sync(&self) -> bool {
+        *self == Self::NotSync
+    }
+
+    fn field_failure(field: Field, error: FrameError<Infallible>) -> Self {
+        match error {
+            FrameError::NotEnoughBits           => Self::NotEnoughBits,
+            FrameError::NotSync                 => Self::NotSync,
+            FrameError::MissingBit(n)           => Self::MissingBit(n),
+            FrameError::ParityError(never)      => match never {},
+            FrameError::InvalidDigit(_, value)  => Self::InvalidDigit(field, value),
+            FrameError::InvalidValue(..) if field == Field::Dut1Sign => Self::InvalidDut1Sign,
+            FrameError::InvalidValue(_, value)  => Self::OutOfRange(field, value as i16),
+        }
+    }
+
+    fn out_of_range(field: Field, value: i16) -> Self {
+        Self::OutOfRange(field, value)
+    }
+}
+
+impl fmt::Display for DecodingFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::NotEnoughBits     => write!(f, "not enough seconds for a full minute"),
+            Self::NotSync           => write!(f, "position markers out of place"),
+            Self::MissingBit(n)     => write!(f, "second {} is missing", n),
+            Self::UnusedBitSet(n)   => write!(f, "unused second {} is set", n),
+            Self::InvalidDut1Sign   => write!(f, "invalid DUT1 sign (seconds 36-38)"),
+            Self::InvalidDigit(field, v) =>
+                write!(f, "invalid {} digit {} ({})", field, v, BitRange(field.bit_range())),
+            Self::OutOfRange(field, v) =>
+                write!(f, "{} {} out of range ({})", field, v, BitRange(field.bit_range())),
+        }
+    }
+}
+
+pub type Error = DecodeError<DecodingFailure>;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WwvbFrame {
+    // Start of the minute the frame was transmitted in
+    pub time: Zoned,
+    pub century: CenturyInference,
+    // UT1 - UTC in tenths of a second
+    pub dut1: i8,
+    pub leap_year: bool,
+    pub leap_second_warning: bool,
+    pub dst: DstStatus
+}
+
+pub struct Decoder {
+    sync: MarkerSync,
+    century: CenturyPolicy
+}
+
+impl fmt::Display for Decoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.sync.fmt(f)
+    }
+}
+
+impl Decoder {
+    pub fn new() -> Decoder {
+        Decoder {
+            sync: MarkerSync::new(LAYOUT),
+            century: CenturyPolicy::default()
+        }
+    }
+
+    pub fn with_century(mut self, century: CenturyPolicy) -> Decoder {
+        self.century = century;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.sync.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.sync.is_full()
+    }
+
+    pub fn add_symbol(&mut self, value: MarkerSymbol) -> &Self {
+        self.sync.add_symbol(value);
+        self
+    }
+
+    pub fn add_pulse(&mut self, pulse: u8) -> &Self {
+        self.add_symbol(decode_pulse(pulse))
+    }
+
+    pub fn add_symbol_iter(&mut self, iter: impl IntoIterator<Item = MarkerSymbol>) -> &Self {
+        for symbol in iter {
+            self.add_symbol(symbol);
+        }
+        self
+    }
+
+    pub fn decode_wwvb(&self) -> Result<Zoned> {
+        self.decode_frame().map(|frame| frame.time)
+    }
+
+    pub fn decode_frame(&self) -> Result<WwvbFrame> {
+        if !self.sync.is_full() {
+            return Err(From::from(DecodingFailure::NotEnoughBits))
+        }
+        let frame = self.sync.frame()
+            .ok_or(DecodingFailure::NotSync)?;
+        let bits = marker_bits(&frame);
+
+        for n in WwvbFields::UNUSED_BITS {
+            match bits[*n].to_bit() {
+                Some(false) => (),
+                Some(true) => return Err(From::from(DecodingFailure::UnusedBitSet(*n))),
+                None => return Err(From::from(DecodingFailure::MissingBit(*n)))
+            }
+        }
+
+        let minute = decode_field(Field::Minute, WwvbFields::decode_minute(&bits), 0..60)?;
+        let hour = decode_field(Field::Hour, WwvbFields::decode_hour(&bits), 0..24)?;
+        let day_of_year = decode_field(Field::DayOfYear, WwvbFields::decode_day_of_year(&bits), 1..367)?;
+        let year = decode_field(Field::Year, WwvbFields::decode_year(&bits), 0..100)?;
+        let dut1_value = decode_field(Field::Dut1, WwvbFields::decode_dut1(&bits), 0..10)? as i8;
+        let dut1 = match decode_value(Field::Dut1Sign, WwvbFields::decode_dut1_sign(&bits))? {
+            Dut1Sign::Positive => dut1_value,
+            Dut1Sign::Negative => -dut1_value,
+        };
+        let leap_year = decode_value(Field::LeapYear, WwvbFields::decode_leap_year(&bits))?;
+        let leap_second_warning = decode_value(Field::LeapSecondWarning, WwvbFields::decode_leap_second_warning(&bits))?;
+        let dst = decode_value(Field::DstStatus, WwvbFields::decode_dst(&bits))?;
+
+        let (full_year, century) = self.century.full_year_without_weekday(year as i8);
+        let date = Date::new(full_year, 1, 1)?
+            .with()
+            .day_of_year(day_of_year)
+            .build()
+            .map_err(|_| DecodingFailure::OutOfRange(Field::DayOfYear, day_of_year))?;
+        let time = date.at(hour as i8, minute as i8, 0, 0)
+            .to_zoned(tz::TimeZone::UTC)?;
+        Ok(WwvbFrame { time, century, dut1, leap_year, leap_second_warning, dst })
+    }
+}
+
+impl TimeCodeDecoder for Decoder {
+    fn name(&self) -> &'static str {
+        "wwvb"
+    }
+
+    fn pulse_timing(&self) -> PulseTiming {
+        PULSE_TIMING
+    }
+
+    fn failure_kinds(&self) -> Vec<&'static str> {
+        time_code::failure_kinds(&DecodingFailure::KINDS)
+    }
+
+    fn pulse_thresholds(&self) -> Vec<(u32, char)> {
+        time_code::width_thresholds(&PULSE_CLASSES)
+    }
+
+    fn frame_len(&self) -> usize {
+        LAYOUT.len
+    }
+
+    fn alphabet(&self) -> &'static str {
+        "01M?_"
+    }
+
+    fn layout(&self) -> &'static [FieldSpan] {
+        WwvbFields::LAYOUT
+    }
+
+    fn feed_pulse(&mut self, pulse: u8) -> char {
+        let symbol = decode_pulse(pulse);
+        self.add_symbol(symbol);
+        symbol_char(symbol)
+    }
+
+    fn feed_symbol(&mut self, symbol: char) -> bool {
+        let Some(symbol) = MarkerSymbol::from_char(symbol) else { return false };
+        self.add_symbol(symbol);
+        true
+    }
+
+    fn is_synced(&self) -> bool {
+        self.sync.is_aligned()
+    }
+
+    fn frame_bits(&self) -> Vec<Bit> {
+        marker_bits(&self.sync.symbols())
+    }
+
+    // The frame gives the minute it was sent in
+    fn decode(&self) -> time_code::Result<DecodedFrame> {
+        let frame = self.decode_frame()?;
+        let next_frame = frame.time.checked_add(SignedDuration::from_mins(1))
+            .map_err(Error::from)?;
+        // Leap seconds so far were all inserted
+        let leap_second = if frame.leap_second_warning { LeapSecond::Inserted } else { LeapSecond::None };
+        // The time is UTC, DST only concerns local time in the US
+        Ok(DecodedFrame {
+            time: frame.time,
+            next_frame,
+            century: frame.century,
+            leap_second,
+            summer_time: false,
+            summer_time_change: false
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::marker_sync::marker_symbols;
+    use MarkerSymbol::*;
+
+    // 2016-07-04 (day 186) 12:34 UTC, DUT1 = -0.3s, DST in effect
+    fn build_valid_signal() -> Vec<MarkerSymbol> {
+        let fields = WwvbFields {
+            minute: 34,
+            hour: 12,
+            day_of_year: 186,
+            dut1_sign: Dut1Sign::Negative,
+            dut1: 3,
+            year: 16,
+            leap_year: true,
+            leap_second_warning: false,
+            dst: DstStatus::InEffect
+        };
+        marker_symbols(&fields.encode_bits().expect("Could not encode the frame"))
+    }
+
+    #[test]
+    fn test_decode_pulse() {
+        assert_eq!(decode_pulse(0b1), Zero);
+        assert_eq!(decode_pulse(0b1111), One);
+        assert_eq!(decode_pulse(0b111_1111), Marker);
+        assert_eq!(decode_pulse(0xFF), Marker);
+        assert_eq!(decode_pulse(0b0), Skipped);
+    }
+
+    #[test]
+    fn test_decoder_valid_signal() {
+        let signal = build_valid_signal();
+        let mut decoder = Decoder::new();
+        // Start in the middle of the previous minute
+        decoder.add_symbol_iter(signal[30..].iter().copied());
+        assert!(matches!(decoder.decode_wwvb(),
+            Err(Error::DecodingError(DecodingFailure::NotEnoughBits))));
+        decoder.add_symbol_iter(signal[..30].iter().copied());
+        assert!(matches!(decoder.decode_wwvb(),
+            Err(Error::DecodingError(DecodingFailure::NotSync))));
+
+        decoder.add_symbol_iter(signal.iter().copied());
+        println!("{}", decoder);
+        let frame = decoder.decode_frame()
+            .expect("Could not decode wwvb time");
+        assert_eq!(frame.time.to_string(), "2016-07-04T12:34:00+00:00[UTC]");
+        assert_eq!(frame.dut1, -3);
+        assert_eq!(frame.dst, DstStatus::InEffect);
+        assert!(frame.leap_year);
+        assert!(!frame.leap_second_warning);
+
+        // WWVB sends no day of week to choose the century by
+        let mut decoder = Decoder::new()
+            .with_century(CenturyPolicy::Weekday(jiff::civil::date(2026, 1, 10)));
+        decoder.add_symbol_iter(signal.iter().copied());
+        let frame = decoder.decode_frame()
+            .expect("Could not decode wwvb time");
+        assert_eq!((frame.time.year(), frame.century), (2016, CenturyInference::NoWeekday));
+    }
+
+    #[test]
+    fn test_decoder_leap_second_warning() {
+        let mut signal = build_valid_signal();
+        signal[56] = One;
+        signal[57] = Zero;
+        let mut decoder = Decoder::new();
+        decoder.add_symbol_iter(signal.iter().copied());
+        let frame = decoder.decode_frame()
+            .expect("Could not decode wwvb time");
+        assert!(frame.leap_second_warning);
+        assert_eq!(frame.dst, DstStatus::EndsToday);
+
+        signal[54] = One;
+        decoder.add_symbol_iter(signal.iter().copied());
+        assert!(matches!(decoder.decode_wwvb(),
+            Err(Error::DecodingError(DecodingFailure::UnusedBitSet(54)))));
+    }
+
+    #[test]
+    fn test_decoder_day_of_year() {
+        // 2015 had no day 366
+        let fields = WwvbFields {
+            minute: 34,
+            hour: 12,
+            day_of_year: 366,
+            dut1_sign: Dut1Sign::Positive,
+            dut1: 0,
+            year: 15,
+            leap_year: false,
+            leap_second_warning: false,
+            dst: DstStatus::Standard
+        };
+        let mut decoder = Decoder::new();
+        decoder.add_symbol_iter(marker_symbols(&fields.encode_bits().expect("Could not encode the frame")));
+        let error = decoder.decode_wwvb().unwrap_err();
+        assert!(matches!(error, Error::DecodingError(DecodingFailure::OutOfRange(Field::DayOfYear, 366))));
+        assert_eq!(error.to_string(), "day of year 366 out of range (bits 22-33)");
+    }
+}